@@ -0,0 +1,304 @@
+use std::path::Path;
+
+use cursor::{parse_file_position, FilePosition};
+use error::RustorError;
+use file::Encoding;
+
+pub const STDIN_FILE_NAME: &str = "-";
+pub const USAGE: &str = "Usage: rustor [OPTIONS] [[+LINE[:COLUMN]] FILE[:LINE[:COLUMN]]]...
+
+Options:
+  -h, --help             Print this help and exit
+  -V, --version          Print the version and exit
+  -R, --readonly         Open the files without allowing changes
+  -c, --config <PATH>    Read the settings from PATH instead of ~/.rustorrc
+  -e, --encoding <NAME>  Read and write the files as UTF-8 (default) or ISO-8859-1
+
+Use - as FILE to edit the text read from the standard input,
+which is the default when the input is piped and no FILE is given.
+Switch between several files with Alt-Left and Alt-Right.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileArgument {
+    pub file_name: String,
+    pub position: Option<FilePosition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arguments {
+    pub files: Vec<FileArgument>,
+    pub is_readonly: bool,
+    pub config_path: Option<String>,
+    pub encoding: Encoding,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Edit(Arguments),
+    Help,
+    Version,
+}
+
+impl FileArgument {
+    fn parse(argument: &str, position: Option<FilePosition>) -> FileArgument {
+        let (file_name, file_position) = split_file_position(argument);
+        FileArgument {
+            file_name,
+            position: position.or(file_position),
+        }
+    }
+
+    pub fn is_stdin(&self) -> bool {
+        self.file_name == STDIN_FILE_NAME
+    }
+}
+
+impl Arguments {
+    pub fn add_piped_stdin(&mut self, is_stdin_piped: bool) {
+        if is_stdin_piped && self.files.is_empty() {
+            self.files.push(FileArgument::parse(STDIN_FILE_NAME, None));
+        }
+    }
+}
+
+fn split_file_position(file_name: &str) -> (String, Option<FilePosition>) {
+    if !Path::new(file_name).exists() {
+        for (index, _) in file_name.match_indices(':') {
+            if let Some(position) = parse_file_position(&file_name[index + 1..]) {
+                return (file_name[..index].to_string(), Some(position));
+            }
+        }
+    }
+    (file_name.to_string(), None)
+}
+
+fn usage_error(message: String) -> RustorError {
+    RustorError::Usage(message)
+}
+
+fn get_option_value<'a, I: Iterator<Item = &'a String>>(
+    name: &str,
+    inline_value: Option<&'a str>,
+    args: &mut I,
+) -> Result<&'a str, RustorError> {
+    match inline_value {
+        Some(value) => Ok(value),
+        None => args
+            .next()
+            .map(String::as_str)
+            .ok_or_else(|| usage_error(format!("Missing value for {}", name))),
+    }
+}
+
+pub fn parse_arguments(args: &[String]) -> Result<Command, RustorError> {
+    let mut arguments = Arguments {
+        files: vec![],
+        is_readonly: false,
+        config_path: None,
+        encoding: Encoding::Utf8,
+    };
+    let mut position = None;
+    let mut are_options_over = false;
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        if are_options_over || arg == STDIN_FILE_NAME || !arg.starts_with(['-', '+']) {
+            arguments
+                .files
+                .push(FileArgument::parse(arg, position.take()));
+            continue;
+        }
+        if let Some(line) = arg.strip_prefix('+') {
+            position = Some(
+                parse_file_position(line)
+                    .ok_or_else(|| usage_error(format!("Invalid line number: {}", arg)))?,
+            );
+            continue;
+        }
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        match name {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-R" | "--readonly" => arguments.is_readonly = true,
+            "-c" | "--config" => {
+                let value = get_option_value(name, inline_value, &mut args)?;
+                arguments.config_path = Some(value.to_string());
+            }
+            "-e" | "--encoding" => {
+                let value = get_option_value(name, inline_value, &mut args)?;
+                arguments.encoding = Encoding::parse(value)
+                    .ok_or_else(|| usage_error(format!("Unsupported encoding: {}", value)))?;
+            }
+            "--" => are_options_over = true,
+            _ => return Err(usage_error(format!("Unknown option: {}", arg))),
+        }
+    }
+    let number_of_stdin_files = arguments
+        .files
+        .iter()
+        .filter(|file| file.is_stdin())
+        .count();
+    if number_of_stdin_files > 1 {
+        return Err(usage_error(String::from(
+            "The standard input can only be read once",
+        )));
+    }
+    Ok(Command::Edit(arguments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, RustorError> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_arguments(&args)
+    }
+
+    fn parse_edit(args: &[&str]) -> Arguments {
+        match parse(args) {
+            Ok(Command::Edit(arguments)) => arguments,
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    fn get_file_names(arguments: &Arguments) -> Vec<&str> {
+        arguments
+            .files
+            .iter()
+            .map(|file| file.file_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn parse_arguments_should_return_no_file_if_no_args() {
+        // Given
+        let args = ["rustor"];
+
+        // When
+        let result = parse_edit(&args);
+
+        // Then
+        assert!(result.files.is_empty());
+        assert!(!result.is_readonly);
+        assert_eq!(result.encoding, Encoding::Utf8);
+    }
+
+    #[test]
+    fn parse_arguments_should_return_the_files_and_options() {
+        // Given
+        let args = [
+            "rustor",
+            "-R",
+            "a.txt",
+            "--config",
+            "my.rc",
+            "--encoding=latin1",
+            "-",
+            "--",
+            "--b.txt",
+        ];
+
+        // When
+        let result = parse_edit(&args);
+
+        // Then
+        assert_eq!(get_file_names(&result), vec!["a.txt", "-", "--b.txt"]);
+        assert!(result.is_readonly);
+        assert!(result.files[1].is_stdin());
+        assert_eq!(result.config_path, Some(String::from("my.rc")));
+        assert_eq!(result.encoding, Encoding::Latin1);
+    }
+
+    #[test]
+    fn parse_arguments_should_read_the_positions() {
+        // Given
+        let args = ["rustor", "+120", "file.rs", "missing.rs:3:7", "other.rs"];
+
+        // When
+        let result = parse_edit(&args);
+
+        // Then
+        assert_eq!(
+            get_file_names(&result),
+            vec!["file.rs", "missing.rs", "other.rs"]
+        );
+        let positions: Vec<_> = result.files.iter().map(|file| file.position).collect();
+        assert_eq!(
+            positions,
+            vec![
+                Some(FilePosition {
+                    line: 120,
+                    column: 1
+                }),
+                Some(FilePosition { line: 3, column: 7 }),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_arguments_should_keep_the_name_of_an_existing_file() {
+        // Given
+        let args = ["rustor", "Cargo.toml"];
+
+        // When
+        let result = parse_edit(&args);
+
+        // Then
+        assert_eq!(result.files[0].file_name, "Cargo.toml");
+        assert_eq!(result.files[0].position, None);
+    }
+
+    #[test]
+    fn parse_arguments_should_stop_at_help_and_version() {
+        assert_eq!(
+            parse(&["rustor", "a.txt", "--help"]).unwrap(),
+            Command::Help
+        );
+        assert_eq!(parse(&["rustor", "-V"]).unwrap(), Command::Version);
+    }
+
+    #[test]
+    fn parse_arguments_should_report_usage_errors() {
+        // Given
+        let cases = [
+            (vec!["rustor", "--color"], "Unknown option: --color"),
+            (vec!["rustor", "--config"], "Missing value for --config"),
+            (
+                vec!["rustor", "-e", "ebcdic"],
+                "Unsupported encoding: ebcdic",
+            ),
+            (vec!["rustor", "+top", "a.txt"], "Invalid line number: +top"),
+            (
+                vec!["rustor", "-", "-"],
+                "The standard input can only be read once",
+            ),
+        ];
+
+        for (args, message) in cases.iter() {
+            // When
+            let result = parse(args);
+
+            // Then
+            assert_eq!(result.unwrap_err().to_string(), *message);
+        }
+    }
+
+    #[test]
+    fn add_piped_stdin_should_only_read_stdin_without_files() {
+        // Given
+        let mut without_files = parse_edit(&["rustor"]);
+        let mut with_files = parse_edit(&["rustor", "a.txt"]);
+
+        // When
+        without_files.add_piped_stdin(true);
+        with_files.add_piped_stdin(true);
+
+        // Then
+        assert_eq!(get_file_names(&without_files), vec!["-"]);
+        assert_eq!(get_file_names(&with_files), vec!["a.txt"]);
+    }
+}
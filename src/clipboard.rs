@@ -0,0 +1,243 @@
+use std::env;
+use std::io::{self, Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+
+use config::Config;
+use error::RustorError;
+
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+struct ClipboardTool {
+    display_variable: &'static str,
+    program: &'static str,
+    copy_command: &'static str,
+    paste_command: &'static str,
+}
+
+const CLIPBOARD_TOOLS: &[ClipboardTool] = &[
+    ClipboardTool {
+        display_variable: "WAYLAND_DISPLAY",
+        program: "wl-copy",
+        copy_command: "wl-copy",
+        paste_command: "wl-paste --no-newline",
+    },
+    ClipboardTool {
+        display_variable: "DISPLAY",
+        program: "xclip",
+        copy_command: "xclip -selection clipboard",
+        paste_command: "xclip -selection clipboard -o",
+    },
+];
+
+#[derive(Debug, Default)]
+pub struct Clipboard {
+    text: String,
+    pending_sequence: Option<String>,
+}
+
+impl Clipboard {
+    pub fn new() -> Clipboard {
+        Clipboard::default()
+    }
+
+    pub fn copy(&mut self, text: String, config: &Config) -> Result<(), RustorError> {
+        self.text = text;
+        if config.osc52 {
+            self.pending_sequence = Some(get_osc52_sequence(&self.text));
+        }
+        let command = config
+            .copy_command
+            .as_deref()
+            .or_else(|| find_clipboard_tool().map(|tool| tool.copy_command));
+        match command {
+            Some(command) => run_copy_command(command, &self.text).map_err(RustorError::Clipboard),
+            None => Ok(()),
+        }
+    }
+
+    /// Falls back to the last copied text when there is no paste command or it fails.
+    pub fn paste(&self, config: &Config) -> String {
+        let command = config
+            .paste_command
+            .as_deref()
+            .or_else(|| find_clipboard_tool().map(|tool| tool.paste_command));
+        command
+            .and_then(|command| run_paste_command(command).ok())
+            .unwrap_or_else(|| self.text.clone())
+    }
+
+    pub fn take_pending_sequence(&mut self) -> Option<String> {
+        self.pending_sequence.take()
+    }
+}
+
+pub fn get_osc52_sequence(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", encode_base64(text.as_bytes()))
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (group >> (18 - 6 * i)) & 0x3f;
+                encoded.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn is_on_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|path| path.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+fn find_clipboard_tool() -> Option<&'static ClipboardTool> {
+    CLIPBOARD_TOOLS
+        .iter()
+        .find(|tool| env::var_os(tool.display_variable).is_some() && is_on_path(tool.program))
+}
+
+fn spawn_shell(command: &str, stdin: Stdio, stdout: Stdio) -> io::Result<Child> {
+    Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(stdin)
+        .stdout(stdout)
+        .stderr(Stdio::null())
+        .spawn()
+}
+
+fn check_status(command: &str, status: ExitStatus) -> io::Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{} exited with {}",
+            command, status
+        )))
+    }
+}
+
+fn run_copy_command(command: &str, text: &str) -> io::Result<()> {
+    let mut child = spawn_shell(command, Stdio::piped(), Stdio::null())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    check_status(command, child.wait()?)
+}
+
+fn run_paste_command(command: &str) -> io::Result<String> {
+    let mut child = spawn_shell(command, Stdio::null(), Stdio::piped())?;
+    let mut text = String::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout.read_to_string(&mut text)?;
+    }
+    check_status(command, child.wait()?)?;
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn get_osc52_sequence_should_encode_the_text_in_base64() {
+        // Given
+        let text = "hello";
+
+        // When
+        let result = get_osc52_sequence(text);
+
+        // Then
+        assert_eq!(result, "\x1b]52;c;aGVsbG8=\x07");
+    }
+
+    #[test]
+    fn encode_base64_should_pad_incomplete_groups() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64("é\n".as_bytes()), "w6kK");
+    }
+
+    #[test]
+    fn copy_should_queue_an_osc52_sequence_when_enabled() {
+        // Given
+        let path = env::temp_dir().join(format!("rustor-clipboard-osc52-{}", process::id()));
+        let path = path.to_string_lossy();
+        let mut clipboard = Clipboard::new();
+        let config = Config::parse(&format!("osc52 = true\ncopy_command = cat > {}", path));
+
+        // When
+        clipboard.copy(String::from("abc"), &config).unwrap();
+
+        // Then
+        assert_eq!(
+            clipboard.take_pending_sequence(),
+            Some(String::from("\x1b]52;c;YWJj\x07"))
+        );
+        assert_eq!(clipboard.take_pending_sequence(), None);
+        assert_eq!(fs::read_to_string(&*path).unwrap(), "abc");
+        fs::remove_file(&*path).unwrap();
+    }
+
+    #[test]
+    fn copy_and_paste_should_use_the_external_commands() {
+        // Given
+        let path = env::temp_dir().join(format!("rustor-clipboard-{}", process::id()));
+        let path = path.to_string_lossy();
+        let config = Config::parse(&format!(
+            "copy_command = cat > {}\npaste_command = cat {}",
+            path, path
+        ));
+        let mut clipboard = Clipboard::new();
+
+        // When
+        clipboard.copy(String::from("from stub"), &config).unwrap();
+        let result = Clipboard::new().paste(&config);
+
+        // Then
+        assert_eq!(result, "from stub");
+        assert_eq!(clipboard.take_pending_sequence(), None);
+        fs::remove_file(&*path).unwrap();
+    }
+
+    #[test]
+    fn copy_should_report_a_failing_command_and_keep_the_text() {
+        // Given
+        let config = Config::parse("copy_command = exit 3");
+        let mut clipboard = Clipboard::new();
+
+        // When
+        let result = clipboard.copy(String::from("kept"), &config);
+
+        // Then
+        assert!(result.is_err());
+        assert_eq!(clipboard.text, "kept");
+    }
+
+    #[test]
+    fn paste_should_fall_back_to_the_copied_text_when_the_command_fails() {
+        // Given
+        let config = Config::parse("copy_command = true\npaste_command = exit 3");
+        let mut clipboard = Clipboard::new();
+        clipboard.copy(String::from("internal"), &config).unwrap();
+
+        // When
+        let result = clipboard.paste(&config);
+
+        // Then
+        assert_eq!(result, "internal");
+    }
+}
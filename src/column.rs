@@ -0,0 +1,348 @@
+use std::cmp;
+use std::ops::Range;
+
+use termion::{color, style};
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HighlightKind {
+    Match,
+    Selection,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub range: Range<usize>,
+    pub kind: HighlightKind,
+}
+
+pub fn get_grapheme_width(grapheme: &str, screen_column: usize, tab_stop: usize) -> usize {
+    match grapheme.chars().next() {
+        Some('\t') => tab_stop - screen_column % tab_stop,
+        Some(c) if c.is_ascii_control() => 2,
+        _ => grapheme.width(),
+    }
+}
+
+fn render_grapheme(grapheme: &str, width: usize, rendered_line: &mut String) {
+    match grapheme.chars().next() {
+        Some('\t') => rendered_line.extend((0..width).map(|_| ' ')),
+        Some(c) if c.is_ascii_control() => {
+            rendered_line.push('^');
+            rendered_line.push((c as u8 ^ 0x40) as char);
+        }
+        _ => rendered_line.push_str(grapheme),
+    }
+}
+
+pub fn get_screen_column(line: &str, x: u16, tab_stop: usize) -> usize {
+    let mut screen_column = 0;
+    for grapheme in line.graphemes(true).take(x as usize - 1) {
+        screen_column += get_grapheme_width(grapheme, screen_column, tab_stop);
+    }
+    screen_column
+}
+
+pub fn get_row_start_columns(line: &str, tab_stop: usize, width: usize) -> Vec<usize> {
+    let mut row_start_columns = vec![0];
+    let mut row_start_column = 0;
+    let mut screen_column = 0;
+    for grapheme in line.graphemes(true) {
+        let grapheme_width = get_grapheme_width(grapheme, screen_column, tab_stop);
+        if screen_column + grapheme_width > row_start_column + width
+            && screen_column > row_start_column
+        {
+            row_start_column = screen_column;
+            row_start_columns.push(row_start_column);
+        }
+        screen_column += grapheme_width;
+    }
+    if screen_column >= row_start_column + width && screen_column > row_start_column {
+        row_start_columns.push(screen_column);
+    }
+    row_start_columns
+}
+
+pub fn get_row_of_screen_column(row_start_columns: &[usize], screen_column: usize) -> usize {
+    row_start_columns.partition_point(|&start| start <= screen_column) - 1
+}
+
+pub fn get_x_at_screen_column(
+    line: &str,
+    screen_column: usize,
+    row_stop_column: usize,
+    tab_stop: usize,
+) -> u16 {
+    let mut x = 1;
+    let mut current_screen_column = 0;
+    for grapheme in line.graphemes(true) {
+        let next_screen_column =
+            current_screen_column + get_grapheme_width(grapheme, current_screen_column, tab_stop);
+        if next_screen_column > screen_column || next_screen_column >= row_stop_column {
+            break;
+        }
+        current_screen_column = next_screen_column;
+        x += 1;
+    }
+    x
+}
+
+pub fn render_line(line: &str, tab_stop: usize, first_column: usize, width: usize) -> String {
+    render_highlighted_line(line, tab_stop, first_column, width, &[])
+}
+
+fn start_highlight(kind: HighlightKind, rendered_line: &mut String) {
+    match kind {
+        HighlightKind::Match => rendered_line.push_str(&format!(
+            "{}{}",
+            color::Bg(color::Yellow),
+            color::Fg(color::Black)
+        )),
+        HighlightKind::Selection => rendered_line.push_str(style::Invert.as_ref()),
+    }
+}
+
+fn stop_highlight(kind: HighlightKind, rendered_line: &mut String) {
+    match kind {
+        HighlightKind::Match => rendered_line.push_str(&format!(
+            "{}{}",
+            color::Bg(color::Reset),
+            color::Fg(color::Reset)
+        )),
+        HighlightKind::Selection => rendered_line.push_str(style::NoInvert.as_ref()),
+    }
+}
+
+pub fn render_highlighted_line(
+    line: &str,
+    tab_stop: usize,
+    first_column: usize,
+    width: usize,
+    highlights: &[Highlight],
+) -> String {
+    let mut rendered_line = String::new();
+    let last_column = first_column + width;
+    let mut screen_column = 0;
+    let mut current_kind = None;
+    for (byte_index, grapheme) in line.grapheme_indices(true) {
+        let grapheme_width = get_grapheme_width(grapheme, screen_column, tab_stop);
+        let next_screen_column = screen_column + grapheme_width;
+        if next_screen_column > last_column {
+            break;
+        }
+        if next_screen_column > first_column {
+            let kind = highlights
+                .iter()
+                .find(|highlight| highlight.range.contains(&byte_index))
+                .map(|highlight| highlight.kind);
+            if kind != current_kind {
+                if let Some(current_kind) = current_kind {
+                    stop_highlight(current_kind, &mut rendered_line);
+                }
+                if let Some(kind) = kind {
+                    start_highlight(kind, &mut rendered_line);
+                }
+                current_kind = kind;
+            }
+        }
+        if screen_column >= first_column {
+            render_grapheme(grapheme, grapheme_width, &mut rendered_line);
+        } else if next_screen_column > first_column {
+            rendered_line.extend((first_column..next_screen_column).map(|_| ' '));
+        }
+        screen_column = next_screen_column;
+    }
+    if let Some(current_kind) = current_kind {
+        stop_highlight(current_kind, &mut rendered_line);
+    }
+    let rendered_width = cmp::max(screen_column, first_column) - first_column;
+    rendered_line.extend((rendered_width..width).map(|_| ' '));
+    rendered_line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_screen_column_should_expand_tabs_to_the_next_tab_stop() {
+        // Given
+        let line = "a\tb\tc";
+
+        // When
+        let result = get_screen_column(line, 5, 4);
+
+        // Then
+        assert_eq!(result, 8);
+    }
+
+    #[test]
+    fn get_screen_column_should_count_wide_characters_twice() {
+        // Given
+        let line = "日本🦀e\u{301}x";
+
+        // When
+        let result = get_screen_column(line, 5, 4);
+
+        // Then
+        assert_eq!(result, 7);
+    }
+
+    #[test]
+    fn render_line_should_expand_tabs_and_pad_the_line() {
+        // Given
+        let line = "\tab\tc";
+
+        // When
+        let result = render_line(line, 4, 0, 12);
+
+        // Then
+        assert_eq!(result, "    ab  c   ");
+    }
+
+    #[test]
+    fn render_line_should_not_cut_a_wide_character() {
+        // Given
+        let line = "ab日本";
+
+        // When
+        let result = render_line(line, 4, 0, 5);
+
+        // Then
+        assert_eq!(result, "ab日 ");
+    }
+
+    #[test]
+    fn render_line_should_show_control_characters() {
+        // Given
+        let line = "line\r";
+
+        // When
+        let result = render_line(line, 4, 0, 6);
+
+        // Then
+        assert_eq!(result, "line^M");
+    }
+
+    #[test]
+    fn render_line_should_skip_the_columns_before_the_first_column() {
+        // Given
+        let line = "\tabcdef";
+
+        // When
+        let result = render_line(line, 4, 5, 3);
+
+        // Then
+        assert_eq!(result, "bcd");
+    }
+
+    #[test]
+    fn render_line_should_hide_a_wide_character_cut_by_the_first_column() {
+        // Given
+        let line = "日本語";
+
+        // When
+        let result = render_line(line, 4, 1, 4);
+
+        // Then
+        assert_eq!(result, " 本 ");
+    }
+
+    #[test]
+    fn get_row_start_columns_should_wrap_before_a_character_that_does_not_fit() {
+        // Given
+        let line = "abcd日本語e";
+
+        // When
+        let result = get_row_start_columns(line, 4, 5);
+
+        // Then
+        assert_eq!(result, vec![0, 4, 8]);
+    }
+
+    #[test]
+    fn get_row_start_columns_should_add_a_row_for_the_end_of_a_full_line() {
+        // Given
+        let line = "abcdefgh";
+
+        // When
+        let result = get_row_start_columns(line, 4, 4);
+
+        // Then
+        assert_eq!(result, vec![0, 4, 8]);
+        assert_eq!(get_row_start_columns("", 4, 4), vec![0]);
+    }
+
+    #[test]
+    fn get_row_of_screen_column_should_find_the_row_containing_the_column() {
+        // Given
+        let row_start_columns = vec![0, 4, 8];
+
+        // When
+        let result = get_row_of_screen_column(&row_start_columns, 7);
+
+        // Then
+        assert_eq!(result, 1);
+        assert_eq!(get_row_of_screen_column(&row_start_columns, 8), 2);
+    }
+
+    #[test]
+    fn get_x_at_screen_column_should_stop_before_the_end_of_the_row() {
+        // Given
+        let line = "abcdefgh";
+
+        // When
+        let result = get_x_at_screen_column(line, 6, 4, 4);
+
+        // Then
+        assert_eq!(result, 4);
+        assert_eq!(get_x_at_screen_column(line, 6, 8, 4), 7);
+    }
+
+    #[test]
+    fn get_x_at_screen_column_should_not_stop_inside_a_wide_character() {
+        // Given
+        let line = "日本語";
+
+        // When
+        let result = get_x_at_screen_column(line, 3, usize::MAX, 4);
+
+        // Then
+        assert_eq!(result, 2);
+    }
+
+    #[test]
+    fn render_highlighted_line_should_highlight_the_ranges() {
+        // Given
+        let line = "a text";
+        let highlights = vec![
+            Highlight {
+                range: 2..4,
+                kind: HighlightKind::Match,
+            },
+            Highlight {
+                range: 4..5,
+                kind: HighlightKind::Selection,
+            },
+        ];
+        let start = format!("{}{}", color::Bg(color::Yellow), color::Fg(color::Black));
+        let stop = format!("{}{}", color::Bg(color::Reset), color::Fg(color::Reset));
+
+        // When
+        let result = render_highlighted_line(line, 4, 0, 8, &highlights);
+
+        // Then
+        assert_eq!(
+            result,
+            format!(
+                "a {}te{}{}x{}t  ",
+                start,
+                stop,
+                style::Invert,
+                style::NoInvert
+            )
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use error::RustorError;
+
+const DEFAULT_TAB_STOP: usize = 4;
+const DEFAULT_WORD_CHARS: &str = "_";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub tab_stop: usize,
+    pub soft_wrap: bool,
+    pub backup: bool,
+    pub osc52: bool,
+    pub copy_command: Option<String>,
+    pub paste_command: Option<String>,
+    pub word_chars: String,
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            tab_stop: DEFAULT_TAB_STOP,
+            soft_wrap: false,
+            backup: false,
+            osc52: false,
+            copy_command: None,
+            paste_command: None,
+            word_chars: String::from(DEFAULT_WORD_CHARS),
+        }
+    }
+
+    pub fn load() -> Config {
+        match get_config_path().and_then(|path| fs::read_to_string(path).ok()) {
+            Some(content) => Config::parse(&content),
+            None => Config::new(),
+        }
+    }
+
+    pub fn load_from(path: &str) -> Result<Config, RustorError> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(Config::parse(&content)),
+            Err(error) => Err(RustorError::Load {
+                file_name: path.to_string(),
+                source: error,
+            }),
+        }
+    }
+
+    pub fn parse(content: &str) -> Config {
+        let mut config = Config::new();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                config.set(key.trim(), value.trim());
+            }
+        }
+        config
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        match key {
+            "tab_stop" => {
+                if let Ok(tab_stop) = value.parse::<usize>() {
+                    self.tab_stop = tab_stop.max(1);
+                }
+            }
+            "soft_wrap" => {
+                if let Ok(soft_wrap) = value.parse() {
+                    self.soft_wrap = soft_wrap;
+                }
+            }
+            "backup" => {
+                if let Ok(backup) = value.parse() {
+                    self.backup = backup;
+                }
+            }
+            "osc52" => {
+                if let Ok(osc52) = value.parse() {
+                    self.osc52 = osc52;
+                }
+            }
+            "copy_command" => self.copy_command = parse_command(value),
+            "paste_command" => self.paste_command = parse_command(value),
+            "word_chars" => self.word_chars = value.to_string(),
+            _ => (),
+        }
+    }
+}
+
+fn parse_command(value: &str) -> Option<String> {
+    match value {
+        "" => None,
+        _ => Some(value.to_string()),
+    }
+}
+
+fn get_config_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".rustorrc"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_should_return_the_default_config_for_an_empty_file() {
+        // Given
+        let content = "";
+
+        // When
+        let result = Config::parse(content);
+
+        // Then
+        assert_eq!(result, Config::new());
+    }
+
+    #[test]
+    fn parse_should_read_the_tab_stop() {
+        // Given
+        let content = "# my config\n\ntab_stop = 8\n";
+
+        // When
+        let result = Config::parse(content);
+
+        // Then
+        assert_eq!(result.tab_stop, 8);
+        assert!(!result.soft_wrap);
+    }
+
+    #[test]
+    fn parse_should_read_the_soft_wrap_mode() {
+        // Given
+        let content = "soft_wrap = true";
+
+        // When
+        let result = Config::parse(content);
+
+        // Then
+        assert!(result.soft_wrap);
+    }
+
+    #[test]
+    fn parse_should_ignore_invalid_values_and_unknown_keys() {
+        // Given
+        let content = "tab_stop = wide\ncolor = blue\nnot a setting";
+
+        // When
+        let result = Config::parse(content);
+
+        // Then
+        assert_eq!(result, Config::new());
+    }
+
+    #[test]
+    fn parse_should_read_the_clipboard_settings() {
+        // Given
+        let content = "osc52 = true\ncopy_command = xclip -selection clipboard\npaste_command =";
+
+        // When
+        let result = Config::parse(content);
+
+        // Then
+        assert!(result.osc52);
+        assert_eq!(
+            result.copy_command,
+            Some(String::from("xclip -selection clipboard"))
+        );
+        assert_eq!(result.paste_command, None);
+    }
+
+    #[test]
+    fn parse_should_read_the_word_chars() {
+        // Given
+        let content = "word_chars = _-$";
+
+        // When
+        let result = Config::parse(content);
+
+        // Then
+        assert_eq!(result.word_chars, "_-$");
+    }
+
+    #[test]
+    fn load_from_should_fail_on_a_missing_file() {
+        // Given
+        let path = "missing_config.rc";
+
+        // When
+        let result = Config::load_from(path);
+
+        // Then
+        assert!(matches!(result, Err(RustorError::Load { .. })));
+    }
+}
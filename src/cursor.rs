@@ -1,10 +1,43 @@
 use std::cmp;
 
-#[derive(Debug, Clone)]
+use unicode_segmentation::UnicodeSegmentation;
+
+use column::*;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct CursorPosition {
     pub x: u16,
     pub y: u16,
     pub y_offset: u16,
+    pub x_offset: u16,
+    pub preferred_column: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilePosition {
+    pub line: usize,
+    pub column: usize,
+}
+
+pub fn parse_file_position(text: &str) -> Option<FilePosition> {
+    let mut parts = text.trim().trim_end_matches(':').splitn(2, ':');
+    let line = parts.next()?.parse().ok()?;
+    let column = match parts.next() {
+        Some(column) => column.parse().ok()?,
+        None => 1,
+    };
+    Some(FilePosition { line, column })
+}
+
+pub fn get_number_of_graphemes(line: &str) -> u16 {
+    line.graphemes(true).count() as u16
+}
+
+pub fn get_byte_index_of_grapheme(line: &str, grapheme_index: usize) -> usize {
+    line.graphemes(true)
+        .take(grapheme_index)
+        .map(str::len)
+        .sum()
 }
 
 impl CursorPosition {
@@ -13,26 +46,50 @@ impl CursorPosition {
             x: 1,
             y: 1,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         }
     }
+    pub fn scroll_horizontally(&mut self, screen_column: usize, text_width: usize) {
+        let screen_column = screen_column as u16;
+        let text_width = cmp::max(text_width, 1) as u16;
+        if screen_column < self.x_offset {
+            self.x_offset = screen_column;
+        } else if screen_column >= self.x_offset + text_width {
+            self.x_offset = screen_column - text_width + 1;
+        }
+    }
+
     pub fn move_left(&mut self) {
         self.x = cmp::max(1, self.x - 1);
+        self.preferred_column = None;
     }
 
-    pub fn move_right(&mut self, lines: &[String]) {
-        let y_position_in_file = self.get_y_position_in_file() as usize;
-        let nb_char_in_current_line = lines[y_position_in_file - 1].len() as u16;
+    pub fn move_right(&mut self, current_line: &str) {
+        let nb_char_in_current_line = get_number_of_graphemes(current_line);
         self.x = cmp::min(self.x + 1, nb_char_in_current_line + 1);
+        self.preferred_column = None;
+    }
+
+    pub fn get_preferred_column(&mut self, current_line: &str, tab_stop: usize) -> usize {
+        let x = self.x;
+        *self
+            .preferred_column
+            .get_or_insert_with(|| get_screen_column(current_line, x, tab_stop))
+    }
+
+    pub fn move_to_preferred_column(&mut self, current_line: &str, column: usize, tab_stop: usize) {
+        self.x = get_x_at_screen_column(current_line, column, usize::MAX, tab_stop);
+        self.preferred_column = Some(column);
     }
 
     pub fn get_y_position_in_file(&self) -> u16 {
         self.y + self.y_offset
     }
 
-    pub fn move_up(&mut self, lines: &[String]) {
-        let y_position_in_file = self.get_y_position_in_file();
-        if y_position_in_file > 1 {
-            let nb_char_in_previous_line = lines[y_position_in_file as usize - 2].len() as u16;
+    pub fn move_up(&mut self, previous_line: Option<&str>) {
+        if let Some(previous_line) = previous_line {
+            let nb_char_in_previous_line = get_number_of_graphemes(previous_line);
             self.x = cmp::min(self.x, nb_char_in_previous_line + 1);
         }
         if self.y == 1 && self.y_offset >= 1 {
@@ -42,13 +99,13 @@ impl CursorPosition {
         }
     }
 
-    pub fn move_down(&mut self, lines: &[String], terminal_height: u16) {
-        let y_position_in_file = self.get_y_position_in_file() as usize;
-        if y_position_in_file == lines.len() {
-            return;
-        }
+    pub fn move_down(&mut self, next_line: Option<&str>, terminal_height: u16) {
+        let next_line = match next_line {
+            Some(next_line) => next_line,
+            None => return,
+        };
         if self.y != terminal_height - 1 {
-            let nb_char_in_next_line = lines[y_position_in_file].len() as u16;
+            let nb_char_in_next_line = get_number_of_graphemes(next_line);
             self.x = cmp::min(self.x, nb_char_in_next_line + 1);
         }
         if self.y == terminal_height - 1 {
@@ -58,30 +115,116 @@ impl CursorPosition {
         }
     }
 
-    pub fn move_to_end_of_line(&mut self, lines: Vec<String>) {
-        let y_position_in_file = self.get_y_position_in_file() as usize;
-        let number_of_char_in_line = lines[y_position_in_file - 1].len() as u16;
+    pub fn move_up_in_wrapped_lines(
+        &mut self,
+        current_line: &str,
+        previous_line: Option<&str>,
+        tab_stop: usize,
+        text_width: usize,
+    ) {
+        let screen_column = get_screen_column(current_line, self.x, tab_stop);
+        let row_start_columns = get_row_start_columns(current_line, tab_stop, text_width);
+        let row = get_row_of_screen_column(&row_start_columns, screen_column);
+        let column_in_row = *self
+            .preferred_column
+            .get_or_insert(screen_column - row_start_columns[row]);
+        if row > 0 {
+            self.x = get_x_at_screen_column(
+                current_line,
+                row_start_columns[row - 1] + column_in_row,
+                row_start_columns[row],
+                tab_stop,
+            );
+        } else if let Some(previous_line) = previous_line {
+            self.move_up(Some(previous_line));
+            let row_start_columns = get_row_start_columns(previous_line, tab_stop, text_width);
+            let last_row_start_column = row_start_columns[row_start_columns.len() - 1];
+            self.x = get_x_at_screen_column(
+                previous_line,
+                last_row_start_column + column_in_row,
+                usize::MAX,
+                tab_stop,
+            );
+        }
+    }
+
+    pub fn move_down_in_wrapped_lines(
+        &mut self,
+        current_line: &str,
+        next_line: Option<&str>,
+        terminal_height: u16,
+        tab_stop: usize,
+        text_width: usize,
+    ) {
+        let screen_column = get_screen_column(current_line, self.x, tab_stop);
+        let row_start_columns = get_row_start_columns(current_line, tab_stop, text_width);
+        let row = get_row_of_screen_column(&row_start_columns, screen_column);
+        let column_in_row = *self
+            .preferred_column
+            .get_or_insert(screen_column - row_start_columns[row]);
+        if row + 1 < row_start_columns.len() {
+            self.x = get_x_at_screen_column(
+                current_line,
+                row_start_columns[row + 1] + column_in_row,
+                *row_start_columns.get(row + 2).unwrap_or(&usize::MAX),
+                tab_stop,
+            );
+        } else if let Some(next_line) = next_line {
+            self.move_down(Some(next_line), terminal_height);
+            let row_start_columns = get_row_start_columns(next_line, tab_stop, text_width);
+            self.x = get_x_at_screen_column(
+                next_line,
+                column_in_row,
+                *row_start_columns.get(1).unwrap_or(&usize::MAX),
+                tab_stop,
+            );
+        }
+    }
+
+    pub fn move_to_end_of_line(&mut self, current_line: &str) {
+        let number_of_char_in_line = get_number_of_graphemes(current_line);
         self.x = number_of_char_in_line + 1;
+        self.preferred_column = None;
     }
 
-    pub fn move_to_end_of_file(&mut self, lines: Vec<String>, terminal_height: u16) {
+    pub fn move_to_end_of_file(&mut self, number_of_lines: usize, terminal_height: u16) {
+        let number_of_lines = number_of_lines as u16;
         self.x = 1;
-        if lines.len() as u16 > terminal_height {
-            self.y_offset = lines.len() as u16 - terminal_height + 1;
+        if number_of_lines > terminal_height {
+            self.y_offset = number_of_lines - terminal_height + 1;
         } else {
             self.y_offset = 0;
         }
-        self.y = lines.len() as u16 - self.y_offset;
+        self.y = number_of_lines - self.y_offset;
+        self.preferred_column = None;
+    }
+
+    pub fn move_page_up(&mut self, terminal_height: u16) {
+        let page_height = terminal_height - 1;
+        let y_position_in_file = self.get_y_position_in_file();
+        self.y_offset = self.y_offset.saturating_sub(page_height);
+        self.y = cmp::max(y_position_in_file.saturating_sub(page_height), 1) - self.y_offset;
+    }
+
+    pub fn move_page_down(&mut self, number_of_lines: usize, terminal_height: u16) {
+        let page_height = terminal_height - 1;
+        let number_of_lines = number_of_lines as u16;
+        let y_position_in_file = self.get_y_position_in_file();
+        let max_y_offset = number_of_lines.saturating_sub(page_height);
+        self.y_offset = cmp::min(self.y_offset + page_height, max_y_offset);
+        self.y = cmp::min(y_position_in_file + page_height, number_of_lines) - self.y_offset;
     }
 
     pub fn move_to_beginning_of_line(&mut self) {
         self.x = 1;
+        self.preferred_column = None;
     }
 
     pub fn move_to_beginning_of_file(&mut self) {
         self.x = 1;
         self.y = 1;
         self.y_offset = 0;
+        self.preferred_column = None;
     }
 }
 
@@ -89,6 +232,33 @@ impl CursorPosition {
 mod tests {
     use super::*;
 
+    #[test]
+    fn should_parse_a_file_position() {
+        assert_eq!(
+            parse_file_position("120"),
+            Some(FilePosition {
+                line: 120,
+                column: 1
+            })
+        );
+        assert_eq!(
+            parse_file_position("120:7:"),
+            Some(FilePosition {
+                line: 120,
+                column: 7
+            })
+        );
+        assert_eq!(parse_file_position("12a"), None);
+        assert_eq!(
+            parse_file_position("12:"),
+            Some(FilePosition {
+                line: 12,
+                column: 1
+            })
+        );
+        assert_eq!(parse_file_position(""), None);
+    }
+
     #[test]
     fn should_move_left() {
         // Given
@@ -96,6 +266,8 @@ mod tests {
             x: 3,
             y: 4,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         };
 
         // When
@@ -114,6 +286,8 @@ mod tests {
             x: 1,
             y: 4,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         };
 
         // When
@@ -132,6 +306,8 @@ mod tests {
             x: 4,
             y: 1,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         };
         let lines: Vec<String> = vec![
             String::from("first line"),
@@ -140,7 +316,7 @@ mod tests {
         ];
 
         // When
-        cursor.move_right(&lines);
+        cursor.move_right(&lines[0]);
 
         // Then
         assert_eq!(cursor.x, 5);
@@ -155,6 +331,8 @@ mod tests {
             x: 11,
             y: 1,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         };
         let lines: Vec<String> = vec![
             String::from("first line"),
@@ -163,7 +341,7 @@ mod tests {
         ];
 
         // When
-        cursor.move_right(&lines);
+        cursor.move_right(&lines[0]);
 
         // Then
         assert_eq!(cursor.x, 11);
@@ -178,6 +356,8 @@ mod tests {
             x: 10,
             y: 4,
             y_offset: 2,
+            x_offset: 0,
+            preferred_column: None,
         };
 
         // When
@@ -194,11 +374,12 @@ mod tests {
             x: 10,
             y: 1,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         };
-        let lines: Vec<String> = vec![String::from("first line")];
 
         // When
-        cursor.move_up(&lines);
+        cursor.move_up(None);
 
         // Then
         assert_eq!(cursor.x, 10);
@@ -213,11 +394,13 @@ mod tests {
             x: 10,
             y: 1,
             y_offset: 1,
+            x_offset: 0,
+            preferred_column: None,
         };
         let lines: Vec<String> = vec![String::from("first"), String::from("we are here")];
 
         // When
-        cursor.move_up(&lines);
+        cursor.move_up(Some(&lines[0]));
 
         // Then
         assert_eq!(cursor.x, 6);
@@ -232,11 +415,13 @@ mod tests {
             x: 10,
             y: 2,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         };
         let lines: Vec<String> = vec![String::from("first"), String::from("we are here")];
 
         // When
-        cursor.move_up(&lines);
+        cursor.move_up(Some(&lines[0]));
 
         // Then
         assert_eq!(cursor.x, 6);
@@ -251,6 +436,8 @@ mod tests {
             x: 7,
             y: 3,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         };
         let lines: Vec<String> = vec![
             String::from("first line"),
@@ -260,7 +447,7 @@ mod tests {
         let terminal_size: u16 = 4;
 
         // When
-        cursor.move_down(&lines, terminal_size);
+        cursor.move_down(lines.get(3).map(String::as_str), terminal_size);
 
         // Then
         assert_eq!(cursor.x, 7);
@@ -275,6 +462,8 @@ mod tests {
             x: 7,
             y: 4,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         };
         let lines: Vec<String> = vec![
             String::from("first line"),
@@ -286,7 +475,7 @@ mod tests {
         let terminal_size: u16 = 5;
 
         // When
-        cursor.move_down(&lines, terminal_size);
+        cursor.move_down(Some(&lines[4]), terminal_size);
 
         // Then
         assert_eq!(cursor.x, 7);
@@ -301,6 +490,8 @@ mod tests {
             x: 7,
             y: 2,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         };
         let lines: Vec<String> = vec![
             String::from("first line"),
@@ -312,7 +503,7 @@ mod tests {
         let terminal_size: u16 = 5;
 
         // When
-        cursor.move_down(&lines, terminal_size);
+        cursor.move_down(Some(&lines[2]), terminal_size);
 
         // Then
         assert_eq!(cursor.x, 7);
@@ -327,6 +518,8 @@ mod tests {
             x: 4,
             y: 3,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         };
         let lines: Vec<String> = vec![
             String::from("first"),
@@ -337,7 +530,7 @@ mod tests {
         ];
 
         // When
-        cursor.move_to_end_of_line(lines);
+        cursor.move_to_end_of_line(&lines[2]);
 
         // Then
         assert_eq!(cursor.x, 11);
@@ -352,6 +545,8 @@ mod tests {
             x: 4,
             y: 3,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         };
         let lines: Vec<String> = vec![
             String::from("first"),
@@ -362,7 +557,7 @@ mod tests {
         ];
 
         // When
-        cursor.move_to_end_of_file(lines, 6);
+        cursor.move_to_end_of_file(lines.len(), 6);
 
         // Then
         assert_eq!(cursor.x, 1);
@@ -377,6 +572,8 @@ mod tests {
             x: 4,
             y: 3,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         };
         let lines: Vec<String> = vec![
             String::from("first"),
@@ -387,7 +584,7 @@ mod tests {
         ];
 
         // When
-        cursor.move_to_end_of_file(lines, 4);
+        cursor.move_to_end_of_file(lines.len(), 4);
 
         // Then
         assert_eq!(cursor.x, 1);
@@ -395,6 +592,63 @@ mod tests {
         assert_eq!(cursor.y_offset, 2);
     }
 
+    #[test]
+    fn should_move_a_page_down() {
+        // Given
+        let mut cursor = CursorPosition {
+            x: 1,
+            y: 2,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+
+        // When
+        cursor.move_page_down(20, 5);
+
+        // Then
+        assert_eq!(cursor.y, 2);
+        assert_eq!(cursor.y_offset, 4);
+    }
+
+    #[test]
+    fn should_move_a_page_down_to_the_last_line() {
+        // Given
+        let mut cursor = CursorPosition {
+            x: 1,
+            y: 2,
+            y_offset: 14,
+            x_offset: 0,
+            preferred_column: None,
+        };
+
+        // When
+        cursor.move_page_down(20, 5);
+
+        // Then
+        assert_eq!(cursor.y, 4);
+        assert_eq!(cursor.y_offset, 16);
+    }
+
+    #[test]
+    fn should_move_a_page_up_to_the_first_line() {
+        // Given
+        let mut cursor = CursorPosition {
+            x: 1,
+            y: 3,
+            y_offset: 2,
+            x_offset: 0,
+            preferred_column: None,
+        };
+
+        // When
+        cursor.move_page_up(5);
+
+        // Then
+        assert_eq!(cursor.y, 1);
+        assert_eq!(cursor.y_offset, 0);
+    }
+
     #[test]
     fn should_move_to_beginning_of_line() {
         // Given
@@ -402,6 +656,8 @@ mod tests {
             x: 4,
             y: 3,
             y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
         };
 
         // When
@@ -420,6 +676,8 @@ mod tests {
             x: 4,
             y: 3,
             y_offset: 1,
+            x_offset: 0,
+            preferred_column: None,
         };
 
         // When
@@ -430,4 +688,189 @@ mod tests {
         assert_eq!(cursor.y, 1);
         assert_eq!(cursor.y_offset, 0);
     }
+
+    #[test]
+    fn should_not_move_right_after_the_last_multi_byte_character() {
+        // Given
+        let mut cursor = CursorPosition {
+            x: 4,
+            y: 1,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines: Vec<String> = vec![String::from("é🦀ü")];
+
+        // When
+        cursor.move_right(&lines[0]);
+
+        // Then
+        assert_eq!(cursor.x, 4);
+    }
+
+    #[test]
+    fn should_move_to_end_of_line_with_combining_characters() {
+        // Given
+        let mut cursor = CursorPosition {
+            x: 1,
+            y: 1,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines: Vec<String> = vec![String::from("cafe\u{301}")];
+
+        // When
+        cursor.move_to_end_of_line(&lines[0]);
+
+        // Then
+        assert_eq!(cursor.x, 5);
+    }
+
+    #[test]
+    fn should_get_byte_index_of_grapheme() {
+        // Given
+        let line = "e\u{301}🦀a";
+
+        // When
+        let result = get_byte_index_of_grapheme(line, 2);
+
+        // Then
+        assert_eq!(result, 7);
+        assert_eq!(get_number_of_graphemes(line), 3);
+    }
+
+    #[test]
+    fn should_scroll_right_when_the_cursor_leaves_the_screen() {
+        // Given
+        let mut cursor = CursorPosition {
+            x: 15,
+            y: 1,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+
+        // When
+        cursor.scroll_horizontally(14, 10);
+
+        // Then
+        assert_eq!(cursor.x_offset, 5);
+    }
+
+    #[test]
+    fn should_scroll_left_when_the_cursor_leaves_the_screen() {
+        // Given
+        let mut cursor = CursorPosition {
+            x: 3,
+            y: 1,
+            y_offset: 0,
+            x_offset: 5,
+            preferred_column: None,
+        };
+
+        // When
+        cursor.scroll_horizontally(2, 10);
+
+        // Then
+        assert_eq!(cursor.x_offset, 2);
+    }
+
+    #[test]
+    fn should_not_scroll_when_the_cursor_is_visible() {
+        // Given
+        let mut cursor = CursorPosition {
+            x: 8,
+            y: 1,
+            y_offset: 0,
+            x_offset: 5,
+            preferred_column: None,
+        };
+
+        // When
+        cursor.scroll_horizontally(7, 10);
+
+        // Then
+        assert_eq!(cursor.x_offset, 5);
+    }
+
+    #[test]
+    fn should_move_up_in_the_same_wrapped_line() {
+        // Given
+        let mut cursor = CursorPosition {
+            x: 7,
+            y: 2,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines: Vec<String> = vec![String::from("first"), String::from("abcdefghij")];
+
+        // When
+        cursor.move_up_in_wrapped_lines(&lines[1], Some(&lines[0]), 4, 4);
+
+        // Then
+        assert_eq!(cursor.x, 3);
+        assert_eq!(cursor.y, 2);
+    }
+
+    #[test]
+    fn should_move_up_to_the_last_row_of_the_previous_wrapped_line() {
+        // Given
+        let mut cursor = CursorPosition {
+            x: 2,
+            y: 2,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines: Vec<String> = vec![String::from("abcdefghij"), String::from("second")];
+
+        // When
+        cursor.move_up_in_wrapped_lines(&lines[1], Some(&lines[0]), 4, 4);
+
+        // Then
+        assert_eq!(cursor.x, 10);
+        assert_eq!(cursor.y, 1);
+    }
+
+    #[test]
+    fn should_move_down_in_the_same_wrapped_line() {
+        // Given
+        let mut cursor = CursorPosition {
+            x: 6,
+            y: 1,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines: Vec<String> = vec![String::from("abcdefghij"), String::from("second")];
+
+        // When
+        cursor.move_down_in_wrapped_lines(&lines[0], Some(&lines[1]), 10, 4, 4);
+
+        // Then
+        assert_eq!(cursor.x, 10);
+        assert_eq!(cursor.y, 1);
+    }
+
+    #[test]
+    fn should_move_down_to_the_first_row_of_the_next_wrapped_line() {
+        // Given
+        let mut cursor = CursorPosition {
+            x: 10,
+            y: 1,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines: Vec<String> = vec![String::from("abcdefghij"), String::from("second")];
+
+        // When
+        cursor.move_down_in_wrapped_lines(&lines[0], Some(&lines[1]), 10, 4, 4);
+
+        // Then
+        assert_eq!(cursor.x, 2);
+        assert_eq!(cursor.y, 2);
+    }
 }
@@ -1,16 +1,80 @@
 use std::cmp;
 use std::io::Error;
+use std::mem;
+use std::ops::Range;
 
 use termion::event::Key;
 
+use clipboard::Clipboard;
+use config::Config;
 use cursor::*;
+use error::RustorError;
 use file::*;
+use history::{Edit, History};
+use minibuffer::Minibuffer;
 use piece_table::PieceTable;
+use replace::{Replace, Replacement};
+use word::*;
+
+const DEFAULT_TEXT_WIDTH: usize = 80;
+const SAVE_AS_LABEL: &str = "Save as: ";
+const SEARCH_LABEL: &str = "Search: ";
+const FAILING_SEARCH_LABEL: &str = "Failing search: ";
+const REPLACE_LABEL: &str = "Replace regex: ";
+const GO_TO_LINE_LABEL: &str = "Go to line[:column]: ";
+const READONLY_MESSAGE: &str = "The file is opened read-only";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mode {
+    Edit,
+    QuitPrompt,
+    SaveAs {
+        minibuffer: Minibuffer,
+        should_quit: bool,
+    },
+    Search {
+        minibuffer: Minibuffer,
+        origin: CursorPosition,
+    },
+    ReplacePattern {
+        minibuffer: Minibuffer,
+    },
+    ReplaceWith {
+        minibuffer: Minibuffer,
+        pattern: String,
+    },
+    ReplaceConfirm {
+        replace: Replace,
+        replacement: Replacement,
+    },
+    GoToLine {
+        minibuffer: Minibuffer,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub anchor: u32,
+    pub is_from_mark: bool,
+}
 
 #[derive(Debug)]
 pub struct Editor {
     piece_table: PieceTable,
     pub cursor: CursorPosition,
+    pub config: Config,
+    pub text_width: usize,
+    pub file_name: Option<String>,
+    pub file_format: FileFormat,
+    saved_file_format: FileFormat,
+    pub message: Option<String>,
+    pub can_overwrite_file: bool,
+    pub mode: Mode,
+    last_search: String,
+    history: History,
+    pub selection: Option<Selection>,
+    clipboard: Clipboard,
+    pub is_readonly: bool,
 }
 
 impl Editor {
@@ -18,7 +82,49 @@ impl Editor {
         Editor {
             piece_table: PieceTable::new(lines.join("\n")),
             cursor: CursorPosition::new(),
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        }
+    }
+
+    pub fn with_config(mut self, config: Config) -> Editor {
+        self.config = config;
+        self
+    }
+
+    pub fn with_file_name(mut self, file_name: Option<String>) -> Editor {
+        self.file_name = file_name;
+        self
+    }
+
+    pub fn with_file_format(mut self, file_format: FileFormat) -> Editor {
+        self.saved_file_format = file_format.clone();
+        self.file_format = file_format;
+        self
+    }
+
+    pub fn with_readonly(mut self, is_readonly: bool) -> Editor {
+        self.is_readonly = is_readonly;
+        self
+    }
+
+    pub fn toggle_line_ending(&mut self) {
+        if self.is_readonly {
+            self.message = Some(String::from(READONLY_MESSAGE));
+            return;
         }
+        self.file_format.line_ending = self.file_format.line_ending.toggle();
     }
 
     pub fn get_editor_lines(&self, terminal_height: usize) -> Vec<String> {
@@ -41,6 +147,7 @@ impl Editor {
         self.piece_table.get_range_lines(start, stop)
     }
 
+    #[allow(dead_code)]
     pub fn get_all_lines(&self) -> Vec<String> {
         self.piece_table.get_all_lines()
     }
@@ -49,13 +156,37 @@ impl Editor {
         self.piece_table.get_number_of_lines()
     }
 
+    fn get_line(&self, line_index: usize) -> Option<String> {
+        self.piece_table.get_line(line_index)
+    }
+
+    pub fn get_current_line(&self) -> String {
+        let y_position_in_file = self.cursor.get_y_position_in_file() as usize;
+        self.get_line(y_position_in_file - 1).unwrap_or_default()
+    }
+
+    pub fn get_previous_line(&self) -> Option<String> {
+        let y_position_in_file = self.cursor.get_y_position_in_file() as usize;
+        if y_position_in_file < 2 {
+            return None;
+        }
+        self.get_line(y_position_in_file - 2)
+    }
+
+    pub fn get_next_line(&self) -> Option<String> {
+        let y_position_in_file = self.cursor.get_y_position_in_file() as usize;
+        self.get_line(y_position_in_file)
+    }
+
     pub fn insert(&mut self, c: char, terminal_height: u16) {
         self.insert_in_piece_table(c, terminal_height);
     }
 
     fn insert_in_piece_table(&mut self, c: char, terminal_height: u16) {
-        self.piece_table
-            .insert(self.get_cursor_position_in_file(), c.to_string());
+        let cursor_before = self.cursor.clone();
+        self.cursor.preferred_column = None;
+        let index = self.get_cursor_position_in_file();
+        self.piece_table.insert(index, c.to_string());
         if c == '\n' && self.cursor.y == terminal_height - 1 {
             self.cursor.x = 1;
             self.cursor.y_offset += 1;
@@ -63,540 +194,2747 @@ impl Editor {
             self.cursor.x = 1;
             self.cursor.y += 1;
         } else {
-            self.cursor.x += 1;
+            let line_index = self.cursor.get_y_position_in_file() as usize - 1;
+            let line_start_index = self.piece_table.get_line_start_index(line_index);
+            let line = self.get_current_line();
+            let text_until_cursor = &line[..(index - line_start_index) as usize + c.len_utf8()];
+            self.cursor.x = get_number_of_graphemes(text_until_cursor) + 1;
         }
+        self.history.record(
+            Edit::Insert {
+                index,
+                text: c.to_string(),
+            },
+            cursor_before,
+            self.cursor.clone(),
+        );
     }
 
     pub fn remove(&mut self, terminal_height: u16) {
         let y_position_in_file = self.cursor.get_y_position_in_file() as usize;
         let start_index = self.get_cursor_position_in_file();
-        if self.cursor.x > 1 {
-            self.piece_table.remove(start_index as u32 - 1, 1);
+        let cursor_before = self.cursor.clone();
+        self.cursor.preferred_column = None;
+        let removed_text = if self.cursor.x > 1 {
+            let line = self.get_current_line();
+            let grapheme_start_index =
+                get_byte_index_of_grapheme(&line, self.cursor.x as usize - 2);
+            let grapheme_length = get_byte_index_of_grapheme(&line, self.cursor.x as usize - 1)
+                - grapheme_start_index;
+            let removed_text =
+                self.remove_in_piece_table(start_index - grapheme_length as u32, grapheme_length);
             self.cursor.x -= 1;
+            removed_text
         } else if y_position_in_file > 1 {
-            let lines = self.get_all_lines();
-            self.piece_table.remove(start_index as u32 - 1, 1);
+            let previous_line = self.get_previous_line().unwrap_or_default();
+            let removed_text = self.remove_in_piece_table(start_index - 1, 1);
             self.cursor.y -= 1;
-            self.cursor.x = (lines[y_position_in_file - 2].len()) as u16 + 1;
+            self.cursor.x = get_number_of_graphemes(&previous_line) + 1;
             if self.cursor.y_offset > 0
                 && self.get_number_of_lines() as u16 - self.cursor.y_offset < terminal_height
             {
                 self.cursor.y_offset -= 1;
                 self.cursor.y += 1;
             }
-        }
+            removed_text
+        } else {
+            return;
+        };
+        self.history.record(
+            Edit::Remove {
+                index: start_index - removed_text.len() as u32,
+                text: removed_text,
+            },
+            cursor_before,
+            self.cursor.clone(),
+        );
     }
-    fn get_cursor_position_in_file(&self) -> u32 {
-        let length = self.cursor.get_y_position_in_file() as usize;
-        let mut lines = self.get_range_lines(0, length);
-        let last_line: String = lines
-            .last_mut()
-            .unwrap()
-            .chars()
-            .take(self.cursor.x as usize - 1)
-            .collect();
-        lines[length - 1] = last_line;
-        lines.join("\n").len() as u32
+
+    fn remove_in_piece_table(&mut self, index: u32, length: usize) -> String {
+        let removed_text = self.piece_table.get_text_range(index, length);
+        self.piece_table.remove(index, length);
+        removed_text
     }
-}
 
-pub fn handle_key_press(
-    key: Result<Key, Error>,
-    editor: &mut Editor,
-    file_name_option: Option<&String>,
-    terminal_height: u16,
-) -> bool {
-    match key.unwrap() {
-        Key::Char(c) => {
-            editor.insert(c, terminal_height);
+    pub fn move_up(&mut self) {
+        let previous_line = self.get_previous_line();
+        if self.config.soft_wrap {
+            let current_line = self.get_current_line();
+            self.cursor.move_up_in_wrapped_lines(
+                &current_line,
+                previous_line.as_deref(),
+                self.config.tab_stop,
+                self.text_width,
+            );
+        } else {
+            let tab_stop = self.config.tab_stop;
+            let column = self
+                .cursor
+                .get_preferred_column(&self.get_current_line(), tab_stop);
+            self.cursor.move_up(previous_line.as_deref());
+            let current_line = self.get_current_line();
+            self.cursor
+                .move_to_preferred_column(&current_line, column, tab_stop);
         }
-        Key::Backspace => {
-            editor.remove(terminal_height);
+    }
+
+    pub fn move_down(&mut self, terminal_height: u16) {
+        let next_line = self.get_next_line();
+        if self.config.soft_wrap {
+            let current_line = self.get_current_line();
+            self.cursor.move_down_in_wrapped_lines(
+                &current_line,
+                next_line.as_deref(),
+                terminal_height,
+                self.config.tab_stop,
+                self.text_width,
+            );
+        } else {
+            let tab_stop = self.config.tab_stop;
+            let column = self
+                .cursor
+                .get_preferred_column(&self.get_current_line(), tab_stop);
+            self.cursor.move_down(next_line.as_deref(), terminal_height);
+            let current_line = self.get_current_line();
+            self.cursor
+                .move_to_preferred_column(&current_line, column, tab_stop);
         }
-        Key::Left => {
-            editor.cursor.move_left();
+    }
+
+    pub fn move_page(&mut self, is_forward: bool, terminal_height: u16) {
+        let tab_stop = self.config.tab_stop;
+        let column = self
+            .cursor
+            .get_preferred_column(&self.get_current_line(), tab_stop);
+        if is_forward {
+            let number_of_lines = self.get_number_of_lines();
+            self.cursor.move_page_down(number_of_lines, terminal_height);
+        } else {
+            self.cursor.move_page_up(terminal_height);
         }
-        Key::Right => {
-            editor.cursor.move_right(&editor.get_all_lines());
+        let line = self.get_current_line();
+        self.cursor
+            .move_to_preferred_column(&line, column, tab_stop);
+    }
+
+    pub fn move_word(&mut self, is_forward: bool, terminal_height: u16) {
+        let (line_index, index) = self.find_word_boundary(is_forward);
+        let line = self.get_line(line_index).unwrap_or_default();
+        let x = get_number_of_graphemes(&line[..index]) + 1;
+        self.move_cursor_within_view(line_index, x, terminal_height);
+    }
+
+    pub fn delete_word(&mut self, is_forward: bool, terminal_height: u16) {
+        if self.get_selection_range().is_some() {
+            self.delete_selection(terminal_height);
+            return;
         }
-        Key::Up => {
-            editor.cursor.move_up(&editor.get_all_lines());
+        self.selection = None;
+        let cursor_before = self.cursor.clone();
+        let cursor_index = self.get_cursor_position_in_file();
+        let (line_index, index) = self.find_word_boundary(is_forward);
+        let boundary_index = self.piece_table.get_line_start_index(line_index) + index as u32;
+        let start_index = cmp::min(cursor_index, boundary_index);
+        let length = (cmp::max(cursor_index, boundary_index) - start_index) as usize;
+        if length == 0 {
+            return;
         }
-        Key::Ctrl('s') => {
-            if let Some(file_name) = file_name_option {
-                save_to_file(file_name, editor.piece_table.get_text()).unwrap();
-            }
+        self.cursor.preferred_column = None;
+        if !is_forward {
+            self.move_word(false, terminal_height);
         }
-        Key::Down => {
-            editor
-                .cursor
-                .move_down(&editor.get_all_lines(), terminal_height);
+        let removed_text = self.remove_in_piece_table(start_index, length);
+        self.history.record(
+            Edit::Remove {
+                index: start_index,
+                text: removed_text,
+            },
+            cursor_before,
+            self.cursor.clone(),
+        );
+    }
+
+    fn find_word_boundary(&self, is_forward: bool) -> (usize, usize) {
+        let word_chars = &self.config.word_chars;
+        let mut line_index = self.cursor.get_y_position_in_file() as usize - 1;
+        let mut line = self.get_current_line();
+        let mut index = get_byte_index_of_grapheme(&line, self.cursor.x as usize - 1);
+        loop {
+            let boundary = if is_forward {
+                find_next_word_end(&line, index, word_chars)
+            } else {
+                find_previous_word_start(&line, index, word_chars)
+            };
+            if let Some(boundary) = boundary {
+                return (line_index, boundary);
+            }
+            let next_line_index = if is_forward {
+                line_index + 1
+            } else {
+                match line_index.checked_sub(1) {
+                    Some(previous_line_index) => previous_line_index,
+                    None => return (0, 0),
+                }
+            };
+            match self.get_line(next_line_index) {
+                Some(next_line) => {
+                    line_index = next_line_index;
+                    index = if is_forward { 0 } else { next_line.len() };
+                    line = next_line;
+                }
+                None => return (line_index, line.len()),
+            }
         }
-        Key::F(1) => {
-            editor.cursor.move_to_beginning_of_line();
+    }
+
+    fn move_cursor_within_view(&mut self, line_index: usize, x: u16, terminal_height: u16) {
+        let number_of_visible_lines = terminal_height as usize - 1;
+        let y_offset = self.cursor.y_offset as usize;
+        if line_index < y_offset {
+            self.cursor.y_offset = line_index as u16;
+        } else if line_index >= y_offset + number_of_visible_lines {
+            self.cursor.y_offset = (line_index + 1 - number_of_visible_lines) as u16;
         }
-        Key::F(2) => {
-            let lines = editor.get_all_lines();
-            editor.cursor.move_to_end_of_line(lines);
+        self.cursor.y = (line_index - self.cursor.y_offset as usize + 1) as u16;
+        self.cursor.x = x;
+        self.cursor.preferred_column = None;
+    }
+
+    pub fn toggle_soft_wrap(&mut self) {
+        self.config.soft_wrap = !self.config.soft_wrap;
+        self.cursor.x_offset = 0;
+        self.cursor.preferred_column = None;
+    }
+
+    pub fn save(&mut self) {
+        match self.file_name.clone() {
+            Some(file_name) => self.save_as(file_name),
+            None => self.open_save_as_prompt(),
         }
-        Key::F(3) => {
-            editor.cursor.move_to_beginning_of_file();
+    }
+
+    pub fn open_save_as_prompt(&mut self) {
+        let file_name = self.file_name.clone().unwrap_or_default();
+        self.mode = Mode::SaveAs {
+            minibuffer: Minibuffer::new(SAVE_AS_LABEL).with_input(&file_name),
+            should_quit: false,
+        };
+    }
+
+    pub fn save_as(&mut self, file_name: String) {
+        let is_same_file = self.file_name.as_ref() == Some(&file_name);
+        if is_same_file && self.is_readonly {
+            self.message = Some(String::from(READONLY_MESSAGE));
+            return;
         }
-        Key::F(4) => {
-            let lines = editor.get_all_lines();
-            editor.cursor.move_to_end_of_file(lines, terminal_height);
+        if is_same_file && !self.can_overwrite_file {
+            self.message = Some(format!(
+                "Not saving {}: it could not be opened and would be overwritten",
+                file_name
+            ));
+            return;
         }
-        Key::Esc => {
-            return false;
+        let text = self.piece_table.get_text();
+        match save_to_file(&file_name, text, &self.file_format, self.config.backup) {
+            Ok(()) => {
+                self.history.mark_saved();
+                self.saved_file_format = self.file_format.clone();
+                let number_of_lines = format_number_of_lines(self.get_number_of_lines());
+                self.message = Some(format!("Saved {}", number_of_lines));
+                self.file_name = Some(file_name);
+                self.can_overwrite_file = true;
+            }
+            Err(error) => self.message = Some(error.to_string()),
         }
-        _ => (),
     }
-    true
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    pub fn is_modified(&self) -> bool {
+        self.history.is_modified() || self.file_format != self.saved_file_format
+    }
 
-    #[test]
-    fn test_handle_key_press_first_char() {
-        // Given
-        let key: Result<Key, Error> = Ok(Key::Char('t'));
-        let terminal_height: u16 = 50;
-        let file_name = String::from("toto");
-        let file_name_option = Some(&file_name);
-        let mut editor = Editor::from(vec![String::new()]);
+    pub fn get_text(&self) -> String {
+        self.piece_table.get_text()
+    }
 
-        // When
-        handle_key_press(key, &mut editor, file_name_option, terminal_height);
+    pub fn undo(&mut self) {
+        if let Some(cursor) = self.history.undo(&mut self.piece_table) {
+            self.cursor = cursor;
+            self.cursor.preferred_column = None;
+        }
+    }
 
-        // Then
-        assert_eq!(editor.cursor.x, 2);
-        assert_eq!(editor.cursor.y, 1);
-        assert_eq!(editor.get_all_lines().len(), 1);
-        assert_eq!(editor.get_editor_lines(20), vec!["t"]);
-        assert_eq!(editor.get_number_of_lines(), 1);
+    pub fn redo(&mut self) {
+        if let Some(cursor) = self.history.redo(&mut self.piece_table) {
+            self.cursor = cursor;
+            self.cursor.preferred_column = None;
+        }
     }
 
-    #[test]
-    fn insert_char_should_insert_first_char_with_piece_table() {
-        // Given
-        let mut editor = Editor::from(vec![String::new()]);
+    pub fn move_cursor_to(&mut self, line_index: usize, x: u16, terminal_height: u16) {
+        let number_of_visible_lines = terminal_height as usize - 1;
+        let y_offset = self.cursor.y_offset as usize;
+        if line_index < y_offset || line_index >= y_offset + number_of_visible_lines {
+            self.cursor.y_offset = line_index.saturating_sub(number_of_visible_lines / 2) as u16;
+        }
+        self.cursor.y = (line_index - self.cursor.y_offset as usize + 1) as u16;
+        self.cursor.x = x;
+        self.cursor.preferred_column = None;
+    }
 
-        // When
-        editor.insert_in_piece_table('x', 36);
+    fn move_cursor_to_index(&mut self, index: u32, terminal_height: u16) {
+        let line_index = self.piece_table.get_line_index(index);
+        let line_start_index = self.piece_table.get_line_start_index(line_index);
+        let line = self.get_line(line_index).unwrap_or_default();
+        let x = get_number_of_graphemes(&line[..(index - line_start_index) as usize]) + 1;
+        self.move_cursor_to(line_index, x, terminal_height);
+    }
 
-        // Then
+    pub fn open_go_to_line_prompt(&mut self) {
+        self.mode = Mode::GoToLine {
+            minibuffer: Minibuffer::new(GO_TO_LINE_LABEL),
+        };
+    }
 
-        assert_eq!(editor.piece_table.get_text(), "x");
-        assert_eq!(editor.cursor.x, 2);
-        assert_eq!(editor.cursor.y, 1);
-        assert_eq!(editor.cursor.y_offset, 0);
+    pub fn go_to_position(&mut self, position: FilePosition, terminal_height: u16) {
+        let line_index = cmp::min(position.line.max(1), self.get_number_of_lines()) - 1;
+        let line = self.get_line(line_index).unwrap_or_default();
+        let number_of_visible_lines = terminal_height as usize - 1;
+        self.selection = None;
+        self.cursor.y_offset = line_index.saturating_sub(number_of_visible_lines / 2) as u16;
+        self.cursor.y = (line_index - self.cursor.y_offset as usize + 1) as u16;
+        self.cursor.x = cmp::min(
+            position.column.max(1) as u16,
+            get_number_of_graphemes(&line) + 1,
+        );
+        self.cursor.preferred_column = None;
     }
 
-    #[test]
-    fn insert_char_should_insert_in_the_middle_of_the_line() {
-        // Given
-        let cursor = CursorPosition {
-            x: 9,
-            y: 1,
-            y_offset: 0,
-        };
-        let lines = vec![String::from("this is  test")];
-        let piece_table = PieceTable::new(lines.clone().join("\n"));
-        let mut editor = Editor {
-            piece_table,
-            cursor,
+    pub fn open_search_prompt(&mut self) {
+        self.mode = Mode::Search {
+            minibuffer: Minibuffer::new(SEARCH_LABEL).with_input(&self.last_search),
+            origin: self.cursor.clone(),
         };
+    }
 
-        // When
-        editor.insert_in_piece_table('a', 36);
+    pub fn get_search_query(&self) -> Option<&str> {
+        match &self.mode {
+            Mode::Search { minibuffer, .. } if !minibuffer.input.is_empty() => {
+                Some(&minibuffer.input)
+            }
+            _ => None,
+        }
+    }
 
-        // Then
-        assert_eq!(editor.piece_table.get_text(), "this is a test");
-        assert_eq!(editor.cursor.x, 10);
-        assert_eq!(editor.cursor.y, 1);
-        assert_eq!(editor.cursor.y_offset, 0);
+    fn search(&mut self, query: &str, index: u32, is_forward: bool, terminal_height: u16) -> bool {
+        if query.is_empty() {
+            return false;
+        }
+        let found_index = if is_forward {
+            self.piece_table
+                .find(query, index)
+                .or_else(|| self.piece_table.find(query, 0))
+        } else {
+            let length = self.piece_table.get_length() as u32;
+            self.piece_table
+                .rfind(query, index)
+                .or_else(|| self.piece_table.rfind(query, length))
+        };
+        match found_index {
+            Some(found_index) => {
+                self.move_cursor_to_index(found_index, terminal_height);
+                true
+            }
+            None => false,
+        }
     }
 
-    #[test]
-    fn insert_char_should_insert_a_new_line_at_the_end() {
-        // Given
-        let cursor = CursorPosition {
-            x: 15,
-            y: 1,
-            y_offset: 0,
+    pub fn open_replace_prompt(&mut self) {
+        self.mode = Mode::ReplacePattern {
+            minibuffer: Minibuffer::new(REPLACE_LABEL),
         };
-        let lines = vec![String::from("this is a test")];
-        let piece_table = PieceTable::new(lines.clone().join("\n"));
-        let mut editor = Editor {
-            piece_table,
-            cursor,
+    }
+
+    pub fn get_replace_match(&self) -> Option<(usize, Range<usize>)> {
+        let replacement = match &self.mode {
+            Mode::ReplaceConfirm { replacement, .. } => replacement,
+            _ => return None,
         };
+        let line_index = self.piece_table.get_line_index(replacement.start as u32);
+        let line_start_index = self.piece_table.get_line_start_index(line_index) as usize;
+        let line_length = self.get_line(line_index).unwrap_or_default().len();
+        let start = replacement.start - line_start_index;
+        let end = cmp::min(replacement.end - line_start_index, line_length);
+        Some((line_index, start..end))
+    }
 
-        // When
-        editor.insert_in_piece_table('\n', 36);
+    fn start_replace(&mut self, pattern: &str, template: &str, terminal_height: u16) {
+        match Replace::new(pattern, template) {
+            Ok(replace) => {
+                self.history.start_group();
+                let text = self.get_text();
+                self.find_next_replacement(replace, &text, terminal_height);
+            }
+            Err(error) => self.message = Some(format!("Invalid regular expression: {}", error)),
+        }
+    }
 
-        // Then
-        assert_eq!(editor.piece_table.get_text(), "this is a test\n");
-        assert_eq!(editor.cursor.x, 1);
-        assert_eq!(editor.cursor.y, 2);
-        assert_eq!(editor.cursor.y_offset, 0);
-        assert_eq!(editor.get_number_of_lines(), 2);
-        assert_eq!(editor.get_editor_lines(20), &vec!["this is a test", ""][..]);
-        assert_eq!(editor.get_editor_lines(1), &vec!["this is a test"][..]);
+    fn find_next_replacement(&mut self, replace: Replace, text: &str, terminal_height: u16) {
+        match replace.find_next(text) {
+            Some(replacement) => {
+                self.move_cursor_to_index(replacement.start as u32, terminal_height);
+                self.mode = Mode::ReplaceConfirm {
+                    replace,
+                    replacement,
+                };
+            }
+            None => self.finish_replace(&replace),
+        }
     }
 
-    #[test]
-    fn insert_char_should_insert_a_new_line_in_the_middle_of_line() {
-        // Given
-        let cursor = CursorPosition {
-            x: 10,
-            y: 1,
-            y_offset: 0,
-        };
-        let lines = vec![String::from("this is a test")];
-        let piece_table = PieceTable::new(lines.clone().join("\n"));
-        let mut editor = Editor {
-            piece_table,
-            cursor,
-        };
+    fn apply_replacement(&mut self, replacement: &Replacement) {
+        let cursor = self.cursor.clone();
+        let index = replacement.start as u32;
+        if replacement.end > replacement.start {
+            let removed_text =
+                self.remove_in_piece_table(index, replacement.end - replacement.start);
+            self.history.record(
+                Edit::Remove {
+                    index,
+                    text: removed_text,
+                },
+                cursor.clone(),
+                cursor.clone(),
+            );
+        }
+        if !replacement.text.is_empty() {
+            self.piece_table.insert(index, replacement.text.clone());
+            self.history.record(
+                Edit::Insert {
+                    index,
+                    text: replacement.text.clone(),
+                },
+                cursor.clone(),
+                cursor,
+            );
+        }
+    }
 
-        // When
-        editor.insert('\n', 36);
+    fn finish_replace(&mut self, replace: &Replace) {
+        self.history.end_group();
+        self.mode = Mode::Edit;
+        let number_of_replacements = replace.number_of_replacements;
+        self.message = Some(match number_of_replacements {
+            1 => String::from("Replaced 1 occurrence"),
+            _ => format!("Replaced {} occurrences", number_of_replacements),
+        });
+    }
 
-        // Then
-        // assert_eq!(editor. vec!["this is a", " test"]);
-        assert_eq!(editor.get_editor_lines(20), vec!["this is a", " test"]);
-        assert_eq!(editor.cursor.x, 1);
-        assert_eq!(editor.cursor.y, 2);
-        assert_eq!(editor.cursor.y_offset, 0);
+    pub fn start_selection(&mut self, is_from_mark: bool) {
+        if self.selection.is_none() {
+            self.selection = Some(Selection {
+                anchor: self.get_cursor_position_in_file(),
+                is_from_mark,
+            });
+        }
     }
 
-    #[test]
-    fn remove_should_remove_char() {
-        // Given
-        let cursor = CursorPosition {
-            x: 11,
-            y: 1,
-            y_offset: 0,
-        };
-        let lines = vec![String::from("this is aw test")];
-        let piece_table = PieceTable::new(lines.clone().join("\n"));
+    pub fn toggle_mark(&mut self) {
+        if self.selection.take().is_none() {
+            self.start_selection(true);
+        }
+    }
 
-        let mut editor = Editor {
-            piece_table,
-            cursor,
-        };
+    pub fn get_selection_range(&self) -> Option<Range<u32>> {
+        let anchor = self.selection.as_ref()?.anchor;
+        let index = self.get_cursor_position_in_file();
+        match anchor.cmp(&index) {
+            cmp::Ordering::Less => Some(anchor..index),
+            cmp::Ordering::Greater => Some(index..anchor),
+            cmp::Ordering::Equal => None,
+        }
+    }
 
-        // When
-        editor.remove(36);
+    pub fn get_selection_in_line(
+        &self,
+        line_index: usize,
+        line_length: usize,
+    ) -> Option<Range<usize>> {
+        let range = self.get_selection_range()?;
+        let line_start_index = self.piece_table.get_line_start_index(line_index);
+        let line_end_index = line_start_index + line_length as u32;
+        if range.end <= line_start_index || range.start > line_end_index {
+            return None;
+        }
+        let start = range.start.saturating_sub(line_start_index) as usize;
+        let end = cmp::min(range.end, line_end_index) - line_start_index;
+        if start < end as usize {
+            Some(start..end as usize)
+        } else {
+            None
+        }
+    }
 
-        // Then
-        assert_eq!(editor.get_editor_lines(36), vec!["this is a test"]);
-        assert_eq!(editor.cursor.x, 10);
-        assert_eq!(editor.cursor.y, 1);
-        assert_eq!(editor.cursor.y_offset, 0);
+    pub fn copy_selection(&mut self) {
+        self.copy_selection_text();
+        self.selection = None;
     }
 
-    #[test]
-    fn remove_should_remove_empty_line() {
-        // Given
-        let cursor = CursorPosition {
-            x: 1,
-            y: 2,
-            y_offset: 0,
+    pub fn cut_selection(&mut self, terminal_height: u16) {
+        self.copy_selection_text();
+        self.delete_selection(terminal_height);
+    }
+
+    pub fn paste(&mut self, terminal_height: u16) {
+        let text = self.clipboard.paste(&self.config);
+        self.replace_selection(&text, terminal_height);
+    }
+
+    pub fn replace_selection(&mut self, text: &str, terminal_height: u16) {
+        self.history.start_group();
+        self.delete_selection(terminal_height);
+        self.insert_text(text, terminal_height);
+        self.history.end_group();
+    }
+
+    pub fn delete_selection(&mut self, terminal_height: u16) {
+        let range = match self.get_selection_range() {
+            Some(range) => range,
+            None => {
+                self.selection = None;
+                return;
+            }
         };
-        let lines = vec![
-            String::from("this is a test"),
-            String::new(),
-            String::from("this is a test2"),
-        ];
+        self.selection = None;
+        let cursor_before = self.cursor.clone();
+        let removed_text =
+            self.remove_in_piece_table(range.start, (range.end - range.start) as usize);
+        self.move_cursor_to_index(range.start, terminal_height);
+        self.history.record(
+            Edit::Remove {
+                index: range.start,
+                text: removed_text,
+            },
+            cursor_before,
+            self.cursor.clone(),
+        );
+    }
+
+    pub fn insert_text(&mut self, text: &str, terminal_height: u16) {
+        if text.is_empty() {
+            return;
+        }
+        let cursor_before = self.cursor.clone();
+        let index = self.get_cursor_position_in_file();
+        self.piece_table.insert(index, text.to_string());
+        self.move_cursor_to_index(index + text.len() as u32, terminal_height);
+        self.history.record(
+            Edit::Insert {
+                index,
+                text: text.to_string(),
+            },
+            cursor_before,
+            self.cursor.clone(),
+        );
+    }
+
+    pub fn take_clipboard_sequence(&mut self) -> Option<String> {
+        self.clipboard.take_pending_sequence()
+    }
+
+    fn copy_selection_text(&mut self) {
+        if let Some(range) = self.get_selection_range() {
+            let text = self
+                .piece_table
+                .get_text_range(range.start, (range.end - range.start) as usize);
+            if let Err(error) = self.clipboard.copy(text, &self.config) {
+                self.message = Some(error.to_string());
+            }
+        }
+    }
+
+    fn get_cursor_position_in_file(&self) -> u32 {
+        let line_index = self.cursor.get_y_position_in_file() as usize - 1;
+        let length_before_cursor =
+            get_byte_index_of_grapheme(&self.get_current_line(), self.cursor.x as usize - 1);
+        self.piece_table.get_line_start_index(line_index) + length_before_cursor as u32
+    }
+}
+
+pub fn format_number_of_lines(number_of_lines: usize) -> String {
+    match number_of_lines {
+        1 => String::from("1 line"),
+        _ => format!("{} lines", number_of_lines),
+    }
+}
+
+pub fn handle_key_press(
+    key: Result<Key, Error>,
+    editor: &mut Editor,
+    terminal_height: u16,
+) -> bool {
+    editor.message = None;
+    let key = match key {
+        Ok(key) => key,
+        Err(error) => {
+            editor.message = Some(RustorError::Terminal(error).to_string());
+            return true;
+        }
+    };
+    match editor.mode {
+        Mode::QuitPrompt => return handle_quit_prompt_key_press(key, editor),
+        Mode::SaveAs { .. } => return handle_save_as_key_press(key, editor),
+        Mode::GoToLine { .. } => return handle_go_to_line_key_press(key, editor, terminal_height),
+        Mode::Search { .. } => return handle_search_key_press(key, editor, terminal_height),
+        Mode::ReplacePattern { .. } | Mode::ReplaceWith { .. } => {
+            return handle_replace_prompt_key_press(key, editor, terminal_height)
+        }
+        Mode::ReplaceConfirm { .. } => {
+            return handle_replace_confirm_key_press(key, editor, terminal_height)
+        }
+        Mode::Edit => (),
+    }
+    if !matches!(key, Key::Char(_)) {
+        editor.history.seal();
+    }
+    if editor.is_readonly && is_editing_key(key) {
+        editor.message = Some(String::from(READONLY_MESSAGE));
+        return true;
+    }
+    match key {
+        Key::ShiftLeft | Key::ShiftRight | Key::ShiftUp | Key::ShiftDown => {
+            editor.start_selection(false);
+        }
+        Key::Char(_)
+        | Key::Backspace
+        | Key::Ctrl('h')
+        | Key::Ctrl('w')
+        | Key::Alt('\x7f')
+        | Key::Alt('d')
+        | Key::Null
+        | Key::Ctrl('c')
+        | Key::Ctrl('x')
+        | Key::Ctrl('v')
+        | Key::Esc => (),
+        _ => {
+            if !matches!(
+                editor.selection,
+                Some(Selection {
+                    is_from_mark: true,
+                    ..
+                })
+            ) {
+                editor.selection = None;
+            }
+        }
+    }
+    match key {
+        Key::Char(c) => {
+            editor.delete_selection(terminal_height);
+            editor.insert(c, terminal_height);
+        }
+        // Terminals configured with `stty erase ^H` send Ctrl-h for Backspace.
+        Key::Backspace | Key::Ctrl('h') if editor.get_selection_range().is_some() => {
+            editor.delete_selection(terminal_height);
+        }
+        Key::Backspace | Key::Ctrl('h') => {
+            editor.selection = None;
+            editor.remove(terminal_height);
+        }
+        Key::Ctrl('z') => {
+            editor.selection = None;
+            editor.undo();
+        }
+        Key::Ctrl('y') => {
+            editor.selection = None;
+            editor.redo();
+        }
+        Key::Null => {
+            editor.toggle_mark();
+        }
+        Key::CtrlLeft => {
+            editor.move_word(false, terminal_height);
+        }
+        Key::CtrlRight => {
+            editor.move_word(true, terminal_height);
+        }
+        Key::Ctrl('w') | Key::Alt('\x7f') => {
+            editor.delete_word(false, terminal_height);
+        }
+        Key::Alt('d') => {
+            editor.delete_word(true, terminal_height);
+        }
+        Key::Ctrl('c') => {
+            editor.copy_selection();
+        }
+        Key::Ctrl('x') => {
+            editor.cut_selection(terminal_height);
+        }
+        Key::Ctrl('v') => {
+            editor.paste(terminal_height);
+        }
+        Key::Left | Key::ShiftLeft => {
+            editor.cursor.move_left();
+        }
+        Key::Right | Key::ShiftRight => {
+            editor.cursor.move_right(&editor.get_current_line());
+        }
+        Key::Up | Key::ShiftUp => {
+            editor.move_up();
+        }
+        Key::Ctrl('s') => {
+            editor.save();
+        }
+        Key::Alt('s') => {
+            editor.open_save_as_prompt();
+        }
+        Key::Ctrl('f') => {
+            editor.open_search_prompt();
+        }
+        Key::Ctrl('r') => {
+            editor.open_replace_prompt();
+        }
+        Key::Ctrl('g') => {
+            editor.open_go_to_line_prompt();
+        }
+        Key::Down | Key::ShiftDown => {
+            editor.move_down(terminal_height);
+        }
+        Key::Alt('z') => {
+            editor.toggle_soft_wrap();
+        }
+        Key::Alt('l') => {
+            editor.toggle_line_ending();
+        }
+        Key::PageUp => {
+            editor.move_page(false, terminal_height);
+        }
+        Key::PageDown => {
+            editor.move_page(true, terminal_height);
+        }
+        Key::Home | Key::F(1) => {
+            editor.cursor.move_to_beginning_of_line();
+        }
+        Key::End | Key::F(2) => {
+            let current_line = editor.get_current_line();
+            editor.cursor.move_to_end_of_line(&current_line);
+        }
+        Key::CtrlHome | Key::F(3) => {
+            editor.cursor.move_to_beginning_of_file();
+        }
+        Key::F(4) => {
+            let number_of_lines = editor.get_number_of_lines();
+            editor
+                .cursor
+                .move_to_end_of_file(number_of_lines, terminal_height);
+        }
+        Key::CtrlEnd => {
+            let number_of_lines = editor.get_number_of_lines();
+            editor
+                .cursor
+                .move_to_end_of_file(number_of_lines, terminal_height);
+            let current_line = editor.get_current_line();
+            editor.cursor.move_to_end_of_line(&current_line);
+        }
+        Key::Esc if editor.selection.is_some() => {
+            editor.selection = None;
+        }
+        Key::Esc => {
+            if !editor.is_modified() {
+                return false;
+            }
+            editor.mode = Mode::QuitPrompt;
+        }
+        _ => (),
+    }
+    true
+}
+
+fn is_editing_key(key: Key) -> bool {
+    matches!(
+        key,
+        Key::Char(_)
+            | Key::Backspace
+            | Key::Ctrl('h')
+            | Key::Ctrl('w')
+            | Key::Ctrl('x')
+            | Key::Ctrl('v')
+            | Key::Ctrl('z')
+            | Key::Ctrl('y')
+            | Key::Ctrl('r')
+            | Key::Alt('\x7f')
+            | Key::Alt('d')
+    )
+}
+
+pub fn handle_paste(text: &str, editor: &mut Editor, terminal_height: u16) {
+    editor.message = None;
+    match editor.mode {
+        Mode::Edit if editor.is_readonly => editor.message = Some(String::from(READONLY_MESSAGE)),
+        Mode::Edit => editor.replace_selection(text, terminal_height),
+        Mode::SaveAs { .. }
+        | Mode::GoToLine { .. }
+        | Mode::Search { .. }
+        | Mode::ReplacePattern { .. }
+        | Mode::ReplaceWith { .. } => {
+            let line = text.lines().next().unwrap_or_default();
+            for c in line.chars() {
+                handle_key_press(Ok(Key::Char(c)), editor, terminal_height);
+            }
+        }
+        Mode::QuitPrompt | Mode::ReplaceConfirm { .. } => (),
+    }
+}
+
+fn handle_quit_prompt_key_press(key: Key, editor: &mut Editor) -> bool {
+    match key {
+        Key::Char('y') => {
+            editor.mode = Mode::Edit;
+            editor.save();
+            if let Mode::SaveAs { should_quit, .. } = &mut editor.mode {
+                *should_quit = true;
+                return true;
+            }
+            return editor.is_modified();
+        }
+        Key::Char('n') => {
+            return false;
+        }
+        Key::Char('c') | Key::Esc => {
+            editor.mode = Mode::Edit;
+        }
+        _ => (),
+    }
+    true
+}
+
+fn handle_search_key_press(key: Key, editor: &mut Editor, terminal_height: u16) -> bool {
+    let (query, origin) = match &mut editor.mode {
+        Mode::Search { minibuffer, origin } => {
+            if !matches!(
+                key,
+                Key::Char('\n') | Key::Esc | Key::Up | Key::Down | Key::Ctrl('f')
+            ) {
+                minibuffer.handle_key_press(key);
+            }
+            (minibuffer.input.clone(), origin.clone())
+        }
+        _ => return true,
+    };
+    let cursor_index = editor.get_cursor_position_in_file();
+    let is_found = match key {
+        Key::Char('\n') | Key::Esc => {
+            if key == Key::Esc {
+                editor.cursor = origin;
+            }
+            editor.last_search = query;
+            editor.mode = Mode::Edit;
+            return true;
+        }
+        Key::Down | Key::Ctrl('f') => {
+            editor.search(&query, cursor_index + 1, true, terminal_height)
+        }
+        Key::Up => editor.search(&query, cursor_index, false, terminal_height),
+        _ => {
+            editor.cursor = origin;
+            let origin_index = editor.get_cursor_position_in_file();
+            editor.search(&query, origin_index, true, terminal_height)
+        }
+    };
+    if let Mode::Search { minibuffer, .. } = &mut editor.mode {
+        minibuffer.label = String::from(if is_found || query.is_empty() {
+            SEARCH_LABEL
+        } else {
+            FAILING_SEARCH_LABEL
+        });
+    }
+    true
+}
+
+fn handle_replace_prompt_key_press(key: Key, editor: &mut Editor, terminal_height: u16) -> bool {
+    let input = match &mut editor.mode {
+        Mode::ReplacePattern { minibuffer } | Mode::ReplaceWith { minibuffer, .. } => match key {
+            Key::Char('\n') => Some(minibuffer.input.clone()),
+            Key::Esc => None,
+            _ => {
+                minibuffer.handle_key_press(key);
+                return true;
+            }
+        },
+        _ => return true,
+    };
+    let mode = mem::replace(&mut editor.mode, Mode::Edit);
+    match (mode, input) {
+        (Mode::ReplacePattern { .. }, Some(pattern)) if !pattern.is_empty() => {
+            if let Err(error) = Replace::new(&pattern, "") {
+                editor.message = Some(format!("Invalid regular expression: {}", error));
+                return true;
+            }
+            editor.mode = Mode::ReplaceWith {
+                minibuffer: Minibuffer::new(&format!("Replace {} with: ", pattern)),
+                pattern,
+            };
+        }
+        (Mode::ReplaceWith { pattern, .. }, Some(template)) => {
+            editor.start_replace(&pattern, &template, terminal_height);
+        }
+        _ => (),
+    }
+    true
+}
+
+fn handle_replace_confirm_key_press(key: Key, editor: &mut Editor, terminal_height: u16) -> bool {
+    let (mut replace, replacement) = match &editor.mode {
+        Mode::ReplaceConfirm {
+            replace,
+            replacement,
+        } => (replace.clone(), replacement.clone()),
+        _ => return true,
+    };
+    match key {
+        Key::Char('y') => {
+            editor.apply_replacement(&replacement);
+            let text = editor.get_text();
+            replace.mark_replaced(&text, &replacement);
+            editor.find_next_replacement(replace, &text, terminal_height);
+        }
+        Key::Char('.') => {
+            editor.apply_replacement(&replacement);
+            replace.mark_replaced(&editor.get_text(), &replacement);
+            editor.finish_replace(&replace);
+        }
+        Key::Char('n') => {
+            let text = editor.get_text();
+            replace.skip(&text, &replacement);
+            editor.find_next_replacement(replace, &text, terminal_height);
+        }
+        Key::Char('a') => {
+            let replacements = replace.find_all(&editor.get_text());
+            for replacement in replacements.iter().rev() {
+                editor.apply_replacement(replacement);
+            }
+            replace.number_of_replacements += replacements.len();
+            editor.finish_replace(&replace);
+        }
+        Key::Char('q') | Key::Esc => {
+            editor.finish_replace(&replace);
+        }
+        _ => (),
+    }
+    true
+}
+
+fn handle_save_as_key_press(key: Key, editor: &mut Editor) -> bool {
+    let (file_name, should_quit) = match &mut editor.mode {
+        Mode::SaveAs {
+            minibuffer,
+            should_quit,
+        } => match key {
+            Key::Char('\n') => (String::from(minibuffer.input.trim()), *should_quit),
+            Key::Esc => (String::new(), false),
+            _ => {
+                minibuffer.handle_key_press(key);
+                return true;
+            }
+        },
+        _ => return true,
+    };
+    editor.mode = Mode::Edit;
+    if file_name.is_empty() {
+        return true;
+    }
+    editor.save_as(file_name);
+    !should_quit || editor.is_modified()
+}
+
+fn handle_go_to_line_key_press(key: Key, editor: &mut Editor, terminal_height: u16) -> bool {
+    let input = match &mut editor.mode {
+        Mode::GoToLine { minibuffer } => match key {
+            Key::Char('\n') => minibuffer.input.clone(),
+            Key::Esc => String::new(),
+            _ => {
+                minibuffer.handle_key_press(key);
+                return true;
+            }
+        },
+        _ => return true,
+    };
+    editor.mode = Mode::Edit;
+    if input.trim().is_empty() {
+        return true;
+    }
+    match parse_file_position(&input) {
+        Some(position) => editor.go_to_position(position, terminal_height),
+        None => editor.message = Some(format!("Invalid line number: {}", input.trim())),
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_handle_key_press_first_char() {
+        // Given
+        let key: Result<Key, Error> = Ok(Key::Char('t'));
+        let terminal_height: u16 = 50;
+        let file_name = String::from("toto");
+        let mut editor = Editor::from(vec![String::new()]).with_file_name(Some(file_name));
+
+        // When
+        handle_key_press(key, &mut editor, terminal_height);
+
+        // Then
+        assert_eq!(editor.cursor.x, 2);
+        assert_eq!(editor.cursor.y, 1);
+        assert_eq!(editor.get_all_lines().len(), 1);
+        assert_eq!(editor.get_editor_lines(20), vec!["t"]);
+        assert_eq!(editor.get_number_of_lines(), 1);
+    }
+
+    #[test]
+    fn test_handle_key_press_undo_should_remove_the_typed_word() {
+        // Given
+        let terminal_height: u16 = 50;
+        let mut editor = Editor::from(vec![String::from("a test")]);
+        editor.cursor.x = 3;
+        for c in "new ".chars() {
+            handle_key_press(Ok(Key::Char(c)), &mut editor, terminal_height);
+        }
+
+        // When
+        handle_key_press(Ok(Key::Ctrl('z')), &mut editor, terminal_height);
+
+        // Then
+        assert_eq!(editor.get_all_lines(), vec!["a test"]);
+        assert_eq!(editor.cursor.x, 3);
+    }
+
+    #[test]
+    fn test_handle_key_press_redo_should_restore_the_removed_line() {
+        // Given
+        let terminal_height: u16 = 50;
+        let lines = vec![String::from("first"), String::from("second")];
+        let mut editor = Editor::from(lines);
+        editor.cursor.y = 2;
+        handle_key_press(Ok(Key::Backspace), &mut editor, terminal_height);
+        handle_key_press(Ok(Key::Ctrl('z')), &mut editor, terminal_height);
+
+        // When
+        handle_key_press(Ok(Key::Ctrl('y')), &mut editor, terminal_height);
+
+        // Then
+        assert_eq!(editor.get_all_lines(), vec!["firstsecond"]);
+        assert_eq!(editor.cursor.x, 6);
+        assert_eq!(editor.cursor.y, 1);
+    }
+
+    #[test]
+    fn undo_should_restore_the_cursor_after_a_backspace() {
+        // Given
+        let cursor = CursorPosition {
+            x: 11,
+            y: 1,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let piece_table = PieceTable::new(String::from("this is aw test"));
+        let mut editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+        editor.remove(36);
+
+        // When
+        editor.undo();
+
+        // Then
+        assert_eq!(editor.get_all_lines(), vec!["this is aw test"]);
+        assert_eq!(editor.cursor.x, 11);
+    }
+
+    #[test]
+    fn undo_should_not_group_characters_typed_after_a_move() {
+        // Given
+        let terminal_height: u16 = 50;
+        let mut editor = Editor::from(vec![String::new()]);
+        handle_key_press(Ok(Key::Char('a')), &mut editor, terminal_height);
+        handle_key_press(Ok(Key::Left), &mut editor, terminal_height);
+        handle_key_press(Ok(Key::Right), &mut editor, terminal_height);
+        handle_key_press(Ok(Key::Char('b')), &mut editor, terminal_height);
+
+        // When
+        editor.undo();
+
+        // Then
+        assert_eq!(editor.get_all_lines(), vec!["a"]);
+        assert_eq!(editor.cursor.x, 2);
+    }
+
+    #[test]
+    fn insert_char_should_insert_first_char_with_piece_table() {
+        // Given
+        let mut editor = Editor::from(vec![String::new()]);
+
+        // When
+        editor.insert_in_piece_table('x', 36);
+
+        // Then
+
+        assert_eq!(editor.piece_table.get_text(), "x");
+        assert_eq!(editor.cursor.x, 2);
+        assert_eq!(editor.cursor.y, 1);
+        assert_eq!(editor.cursor.y_offset, 0);
+    }
+
+    #[test]
+    fn insert_char_should_insert_in_the_middle_of_the_line() {
+        // Given
+        let cursor = CursorPosition {
+            x: 9,
+            y: 1,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines = vec![String::from("this is  test")];
         let piece_table = PieceTable::new(lines.clone().join("\n"));
+        let mut editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        editor.insert_in_piece_table('a', 36);
+
+        // Then
+        assert_eq!(editor.piece_table.get_text(), "this is a test");
+        assert_eq!(editor.cursor.x, 10);
+        assert_eq!(editor.cursor.y, 1);
+        assert_eq!(editor.cursor.y_offset, 0);
+    }
+
+    #[test]
+    fn insert_char_should_insert_a_new_line_at_the_end() {
+        // Given
+        let cursor = CursorPosition {
+            x: 15,
+            y: 1,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines = vec![String::from("this is a test")];
+        let piece_table = PieceTable::new(lines.clone().join("\n"));
+        let mut editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        editor.insert_in_piece_table('\n', 36);
+
+        // Then
+        assert_eq!(editor.piece_table.get_text(), "this is a test\n");
+        assert_eq!(editor.cursor.x, 1);
+        assert_eq!(editor.cursor.y, 2);
+        assert_eq!(editor.cursor.y_offset, 0);
+        assert_eq!(editor.get_number_of_lines(), 2);
+        assert_eq!(editor.get_editor_lines(20), &vec!["this is a test", ""][..]);
+        assert_eq!(editor.get_editor_lines(1), &vec!["this is a test"][..]);
+    }
+
+    #[test]
+    fn insert_char_should_insert_a_new_line_in_the_middle_of_line() {
+        // Given
+        let cursor = CursorPosition {
+            x: 10,
+            y: 1,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines = vec![String::from("this is a test")];
+        let piece_table = PieceTable::new(lines.clone().join("\n"));
+        let mut editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        editor.insert('\n', 36);
+
+        // Then
+        // assert_eq!(editor. vec!["this is a", " test"]);
+        assert_eq!(editor.get_editor_lines(20), vec!["this is a", " test"]);
+        assert_eq!(editor.cursor.x, 1);
+        assert_eq!(editor.cursor.y, 2);
+        assert_eq!(editor.cursor.y_offset, 0);
+    }
+
+    #[test]
+    fn remove_should_remove_char() {
+        // Given
+        let cursor = CursorPosition {
+            x: 11,
+            y: 1,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines = vec![String::from("this is aw test")];
+        let piece_table = PieceTable::new(lines.clone().join("\n"));
+
+        let mut editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        editor.remove(36);
+
+        // Then
+        assert_eq!(editor.get_editor_lines(36), vec!["this is a test"]);
+        assert_eq!(editor.cursor.x, 10);
+        assert_eq!(editor.cursor.y, 1);
+        assert_eq!(editor.cursor.y_offset, 0);
+    }
+
+    #[test]
+    fn remove_should_remove_empty_line() {
+        // Given
+        let cursor = CursorPosition {
+            x: 1,
+            y: 2,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines = vec![
+            String::from("this is a test"),
+            String::new(),
+            String::from("this is a test2"),
+        ];
+        let piece_table = PieceTable::new(lines.clone().join("\n"));
+
+        let mut editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        editor.remove(36);
+
+        // Then
+        assert_eq!(editor.cursor.x, 15);
+        assert_eq!(editor.cursor.y, 1);
+        assert_eq!(editor.cursor.y_offset, 0);
+        assert_eq!(
+            editor.get_editor_lines(36),
+            vec!["this is a test", "this is a test2"]
+        );
+    }
+
+    #[test]
+    fn remove_should_remove_not_empty_line() {
+        // Given
+        let cursor = CursorPosition {
+            x: 1,
+            y: 2,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines = vec![
+            String::from("this is a test"),
+            String::from("this is a test2"),
+            String::from("this is a test3"),
+        ];
+        let piece_table = PieceTable::new(lines.clone().join("\n"));
+
+        let mut editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        editor.remove(36);
+
+        // Then
+        assert_eq!(
+            editor.get_editor_lines(36),
+            vec!["this is a testthis is a test2", "this is a test3"]
+        );
+        assert_eq!(editor.cursor.x, 15);
+        assert_eq!(editor.cursor.y, 1);
+        assert_eq!(editor.cursor.y_offset, 0);
+    }
+
+    #[test]
+    fn remove_should_not_remove() {
+        // Given
+        let cursor = CursorPosition {
+            x: 1,
+            y: 1,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines = vec![
+            String::from("this is a test"),
+            String::from("this is a test2"),
+        ];
+        let piece_table = PieceTable::new(lines.clone().join("\n"));
+
+        let mut editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        editor.remove(36);
+
+        // Then
+        assert_eq!(
+            editor.get_editor_lines(36),
+            vec!["this is a test", "this is a test2"]
+        );
+        assert_eq!(editor.cursor.x, 1);
+        assert_eq!(editor.cursor.y, 1);
+        assert_eq!(editor.cursor.y_offset, 0);
+    }
+
+    #[test]
+    fn remove_should_remove_char_with_offset() {
+        // Given
+        let cursor = CursorPosition {
+            x: 5,
+            y: 3,
+            y_offset: 1,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines = vec![
+            String::from("this is a test"),
+            String::from("this is a test2"),
+            String::from("this is a test3"),
+            String::from("this is a test4"),
+        ];
+        let piece_table = PieceTable::new(lines.clone().join("\n"));
+
+        let mut editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        editor.remove(3);
+
+        // Then
+        assert_eq!(
+            editor.get_range_lines(0, 4),
+            vec![
+                "this is a test",
+                "this is a test2",
+                "this is a test3",
+                "thi is a test4"
+            ]
+        );
+        assert_eq!(editor.cursor.x, 4);
+        assert_eq!(editor.cursor.y, 3);
+        assert_eq!(editor.cursor.y_offset, 1);
+    }
+
+    #[test]
+    fn remove_should_remove_char_with_offset_on_first_line() {
+        // Given
+        let cursor = CursorPosition {
+            x: 5,
+            y: 1,
+            y_offset: 1,
+            x_offset: 0,
+            preferred_column: None,
+        };
+
+        let lines = vec![
+            String::from("this is a test"),
+            String::from("this is a test2"),
+            String::from("this is a test3"),
+            String::from("this is a test4"),
+        ];
+        let piece_table = PieceTable::new(lines.clone().join("\n"));
+
+        let mut editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        editor.remove(36);
+
+        // Then
+        assert_eq!(
+            editor.get_range_lines(0, 4),
+            vec![
+                "this is a test",
+                "thi is a test2",
+                "this is a test3",
+                "this is a test4"
+            ]
+        );
+        assert_eq!(editor.cursor.x, 4);
+        assert_eq!(editor.cursor.y, 1);
+        assert_eq!(editor.cursor.y_offset, 1);
+    }
+
+    #[test]
+    fn remove_should_remove_line_with_offset_reduced() {
+        // Given
+        let cursor = CursorPosition {
+            x: 1,
+            y: 1,
+            y_offset: 1,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines = vec![
+            String::from("this is a test"),
+            String::from("this is a test2"),
+            String::from("this is a test3"),
+            String::from("this is a test4"),
+        ];
+        let piece_table = PieceTable::new(lines.clone().join("\n"));
+
+        let mut editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        editor.remove(5);
+
+        // Then
+        assert_eq!(
+            editor.get_range_lines(0, 4),
+            vec![
+                "this is a testthis is a test2",
+                "this is a test3",
+                "this is a test4"
+            ]
+        );
+        assert_eq!(editor.cursor.x, 15);
+        assert_eq!(editor.cursor.y, 1);
+        assert_eq!(editor.cursor.y_offset, 0);
+    }
+
+    #[test]
+    fn remove_should_remove_last_empty_line() {
+        // Given
+        let cursor = CursorPosition {
+            x: 1,
+            y: 4,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines = vec![
+            String::from("this is a test"),
+            String::from(""),
+            String::from(""),
+            String::from(""),
+        ];
+        let piece_table = PieceTable::new(lines.clone().join("\n"));
+
+        let mut editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        editor.remove(5);
+
+        // Then
+        assert_eq!(editor.get_range_lines(0, 4), vec!["this is a test", "", ""]);
+        assert_eq!(editor.cursor.x, 1);
+        assert_eq!(editor.cursor.y, 3);
+        assert_eq!(editor.cursor.y_offset, 0);
+    }
+
+    #[test]
+    fn get_cursor_position_in_file_should_compute_nb_of_characters() {
+        // Given
+        let cursor = CursorPosition {
+            x: 1,
+            y: 2,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines = vec![
+            String::from("this is a test"),
+            String::from("this is a test2"),
+            String::from("this is a test3"),
+        ];
+        let piece_table = PieceTable::new(lines.clone().join("\n"));
+
+        let editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        let result = editor.get_cursor_position_in_file();
+
+        // Then
+        assert_eq!(result, 15);
+    }
+
+    #[test]
+    fn get_cursor_position_in_file_should_compute_nb_of_characters_with_offset() {
+        // Given
+        let cursor = CursorPosition {
+            x: 5,
+            y: 3,
+            y_offset: 1,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let lines = vec![
+            String::from("this is a test"),
+            String::from("this is a test2"),
+            String::from("this is a test3"),
+            String::from("this is a test4"),
+        ];
+        let piece_table = PieceTable::new(lines.clone().join("\n"));
+
+        let editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        let result = editor.get_cursor_position_in_file();
+
+        // Then
+        assert_eq!(result, 51);
+    }
+
+    #[test]
+    fn insert_char_should_insert_multi_byte_characters() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("ca")]);
+        editor.cursor.x = 3;
+
+        // When
+        editor.insert('f', 36);
+        editor.insert('é', 36);
+        editor.insert('🦀', 36);
+
+        // Then
+        assert_eq!(editor.get_all_lines(), vec!["café🦀"]);
+        assert_eq!(editor.cursor.x, 6);
+    }
+
+    #[test]
+    fn insert_char_should_not_move_the_cursor_for_a_combining_character() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("cafe")]);
+        editor.cursor.x = 5;
+
+        // When
+        editor.insert('\u{301}', 36);
+        editor.insert('!', 36);
+
+        // Then
+        assert_eq!(editor.get_all_lines(), vec!["cafe\u{301}!"]);
+        assert_eq!(editor.cursor.x, 6);
+    }
+
+    #[test]
+    fn remove_should_remove_a_multi_byte_character() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("a🦀é")]);
+        editor.cursor.x = 3;
+
+        // When
+        editor.remove(36);
+
+        // Then
+        assert_eq!(editor.get_all_lines(), vec!["aé"]);
+        assert_eq!(editor.cursor.x, 2);
+    }
+
+    #[test]
+    fn remove_should_remove_the_whole_grapheme_with_combining_characters() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("cafe\u{301}s")]);
+        editor.cursor.x = 5;
+
+        // When
+        editor.remove(36);
+        editor.undo();
+        editor.remove(36);
+
+        // Then
+        assert_eq!(editor.get_all_lines(), vec!["cafs"]);
+        assert_eq!(editor.cursor.x, 4);
+    }
+
+    #[test]
+    fn remove_should_put_the_cursor_after_the_multi_byte_previous_line() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("été"), String::from("x")]);
+        editor.cursor.y = 2;
+
+        // When
+        editor.remove(36);
+
+        // Then
+        assert_eq!(editor.get_all_lines(), vec!["étéx"]);
+        assert_eq!(editor.cursor.x, 4);
+    }
+
+    #[test]
+    fn get_cursor_position_in_file_should_count_bytes_of_multi_byte_characters() {
+        // Given
+        let cursor = CursorPosition {
+            x: 3,
+            y: 2,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        };
+        let piece_table = PieceTable::new(String::from("🦀\ne\u{301}té"));
+        let editor = Editor {
+            piece_table,
+            cursor,
+            config: Config::new(),
+            text_width: DEFAULT_TEXT_WIDTH,
+            file_name: None,
+            file_format: FileFormat::new(),
+            saved_file_format: FileFormat::new(),
+            message: None,
+            can_overwrite_file: true,
+            mode: Mode::Edit,
+            last_search: String::new(),
+            history: History::new(),
+            selection: None,
+            clipboard: Clipboard::new(),
+            is_readonly: false,
+        };
+
+        // When
+        let result = editor.get_cursor_position_in_file();
+
+        // Then
+        assert_eq!(result, 9);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_move_down_by_visual_row_in_soft_wrap_mode() {
+        // Given
+        let terminal_height: u16 = 50;
+        let lines = vec![
+            String::from("a line longer than the text"),
+            String::from("end"),
+        ];
+        let mut editor = Editor::from(lines);
+        editor.text_width = 10;
+        editor.cursor.x = 3;
+        handle_key_press(Ok(Key::Alt('z')), &mut editor, terminal_height);
+
+        // When
+        handle_key_press(Ok(Key::Down), &mut editor, terminal_height);
+
+        // Then
+        assert!(editor.config.soft_wrap);
+        assert_eq!(editor.cursor.x, 13);
+        assert_eq!(editor.cursor.y, 1);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_move_down_by_line_without_soft_wrap() {
+        // Given
+        let terminal_height: u16 = 50;
+        let lines = vec![
+            String::from("a line longer than the text"),
+            String::from("end"),
+        ];
+        let mut editor = Editor::from(lines);
+        editor.text_width = 10;
+        editor.cursor.x = 3;
+
+        // When
+        handle_key_press(Ok(Key::Down), &mut editor, terminal_height);
+
+        // Then
+        assert_eq!(editor.cursor.x, 3);
+        assert_eq!(editor.cursor.y, 2);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_toggle_the_line_ending() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("text")]);
+
+        // When
+        handle_key_press(Ok(Key::Alt('l')), &mut editor, 50);
+
+        // Then
+        assert_eq!(editor.file_format.line_ending, LineEnding::CRLF);
+        assert!(editor.is_modified());
+    }
+
+    #[test]
+    fn test_handle_key_press_should_ask_before_quitting_after_a_line_ending_toggle() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("text")]);
+        handle_key_press(Ok(Key::Alt('l')), &mut editor, 50);
+
+        // When
+        let should_continue = handle_key_press(Ok(Key::Esc), &mut editor, 50);
+
+        // Then
+        assert!(should_continue);
+        assert_eq!(editor.mode, Mode::QuitPrompt);
+    }
+
+    #[test]
+    fn test_is_modified_should_be_false_after_toggling_the_line_ending_back() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("text")]);
+
+        // When
+        press_keys(&mut editor, &[Key::Alt('l'), Key::Alt('l')]);
+
+        // Then
+        assert!(!editor.is_modified());
+    }
+
+    #[test]
+    fn test_handle_key_press_should_not_overwrite_a_file_that_failed_to_load() {
+        // Given
+        let file_name = String::from("test_editor_protected_file.txt");
+        fs::write(&file_name, "content").unwrap();
+        let mut editor = Editor::from(vec![String::new()]).with_file_name(Some(file_name.clone()));
+        editor.can_overwrite_file = false;
+
+        // When
+        handle_key_press(Ok(Key::Ctrl('s')), &mut editor, 50);
+
+        // Then
+        assert!(editor.message.is_some());
+        assert_eq!(fs::read_to_string(&file_name).unwrap(), "content");
+
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    fn test_handle_key_press_should_report_a_failed_save() {
+        // Given
+        let file_name = String::from("missing_directory/test_file.txt");
+        let mut editor = Editor::from(vec![String::from("text")]).with_file_name(Some(file_name));
+
+        // When
+        handle_key_press(Ok(Key::Ctrl('s')), &mut editor, 50);
+
+        // Then
+        let message = editor.message.unwrap();
+        assert!(message.starts_with("Could not save missing_directory/test_file.txt"));
+    }
+
+    #[test]
+    fn test_handle_key_press_should_clear_the_modified_flag_on_save() {
+        // Given
+        let file_name = String::from("test_editor_modified_file.txt");
+        let mut editor = Editor::from(vec![String::new()]).with_file_name(Some(file_name.clone()));
+        handle_key_press(Ok(Key::Char('a')), &mut editor, 50);
+        assert!(editor.is_modified());
+
+        // When
+        handle_key_press(Ok(Key::Ctrl('s')), &mut editor, 50);
+
+        // Then
+        assert!(!editor.is_modified());
+        assert_eq!(editor.message, Some(String::from("Saved 1 line")));
+
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    fn test_handle_key_press_should_quit_an_unmodified_buffer() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("text")]);
+
+        // When
+        let result = handle_key_press(Ok(Key::Esc), &mut editor, 50);
+
+        // Then
+        assert!(!result);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_ask_before_quitting_a_modified_buffer() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("text")]);
+        handle_key_press(Ok(Key::Char('a')), &mut editor, 50);
+
+        // When
+        let result = handle_key_press(Ok(Key::Esc), &mut editor, 50);
+
+        // Then
+        assert!(result);
+        assert_eq!(editor.mode, Mode::QuitPrompt);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_discard_the_changes_when_asked_to() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("text")]);
+        handle_key_press(Ok(Key::Char('a')), &mut editor, 50);
+        handle_key_press(Ok(Key::Esc), &mut editor, 50);
+
+        // When
+        let result = handle_key_press(Ok(Key::Char('n')), &mut editor, 50);
+
+        // Then
+        assert!(!result);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_cancel_quitting() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("text")]);
+        handle_key_press(Ok(Key::Char('a')), &mut editor, 50);
+        handle_key_press(Ok(Key::Esc), &mut editor, 50);
+
+        // When
+        let result = handle_key_press(Ok(Key::Char('c')), &mut editor, 50);
+
+        // Then
+        assert!(result);
+        assert_eq!(editor.mode, Mode::Edit);
+        assert_eq!(editor.get_text(), "atext");
+    }
+
+    #[test]
+    fn test_handle_key_press_should_save_before_quitting() {
+        // Given
+        let file_name = String::from("test_editor_save_before_quitting.txt");
+        let mut editor = Editor::from(vec![String::new()]).with_file_name(Some(file_name.clone()));
+        handle_key_press(Ok(Key::Char('a')), &mut editor, 50);
+        handle_key_press(Ok(Key::Esc), &mut editor, 50);
+
+        // When
+        let result = handle_key_press(Ok(Key::Char('y')), &mut editor, 50);
+
+        // Then
+        assert!(!result);
+        assert_eq!(fs::read_to_string(&file_name).unwrap(), "a\n");
+
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    fn test_handle_key_press_should_not_quit_when_the_save_fails() {
+        // Given
+        let file_name = String::from("missing_directory/test_file.txt");
+        let mut editor = Editor::from(vec![String::new()]).with_file_name(Some(file_name));
+        handle_key_press(Ok(Key::Char('a')), &mut editor, 50);
+        handle_key_press(Ok(Key::Esc), &mut editor, 50);
+
+        // When
+        let result = handle_key_press(Ok(Key::Char('y')), &mut editor, 50);
+
+        // Then
+        assert!(result);
+        assert_eq!(editor.mode, Mode::Edit);
+        assert!(editor.message.is_some());
+    }
+
+    #[test]
+    fn test_handle_key_press_should_ask_for_a_file_name_to_save_an_unnamed_buffer() {
+        // Given
+        let file_name = "test_editor_save_as.txt";
+        let mut editor = Editor::from(vec![String::new()]);
+        handle_key_press(Ok(Key::Char('a')), &mut editor, 50);
+
+        // When
+        handle_key_press(Ok(Key::Ctrl('s')), &mut editor, 50);
+        for c in file_name.chars() {
+            handle_key_press(Ok(Key::Char(c)), &mut editor, 50);
+        }
+        handle_key_press(Ok(Key::Char('\n')), &mut editor, 50);
+
+        // Then
+        assert_eq!(editor.mode, Mode::Edit);
+        assert_eq!(editor.file_name, Some(String::from(file_name)));
+        assert!(!editor.is_modified());
+        assert_eq!(editor.get_text(), "a");
+        assert_eq!(fs::read_to_string(file_name).unwrap(), "a\n");
+
+        fs::remove_file(file_name).unwrap();
+    }
+
+    #[test]
+    fn test_handle_key_press_should_switch_to_the_file_saved_as() {
+        // Given
+        let file_name = String::from("test_editor_protected_original.txt");
+        let new_file_name = "test_editor_protected_copy.txt";
+        let mut editor = Editor::from(vec![String::new()]).with_file_name(Some(file_name));
+        editor.can_overwrite_file = false;
+        handle_key_press(Ok(Key::Alt('s')), &mut editor, 50);
+        for _ in 0.."original.txt".len() {
+            handle_key_press(Ok(Key::Backspace), &mut editor, 50);
+        }
+
+        // When
+        for c in "copy.txt\n".chars() {
+            handle_key_press(Ok(Key::Char(c)), &mut editor, 50);
+        }
+
+        // Then
+        assert_eq!(editor.file_name, Some(String::from(new_file_name)));
+        assert!(editor.can_overwrite_file);
+        assert!(fs::metadata(new_file_name).is_ok());
+
+        fs::remove_file(new_file_name).unwrap();
+    }
+
+    #[test]
+    fn test_handle_key_press_should_cancel_save_as() {
+        // Given
+        let mut editor = Editor::from(vec![String::new()]);
+        handle_key_press(Ok(Key::Alt('s')), &mut editor, 50);
+        handle_key_press(Ok(Key::Char('x')), &mut editor, 50);
+
+        // When
+        handle_key_press(Ok(Key::Esc), &mut editor, 50);
+
+        // Then
+        assert_eq!(editor.mode, Mode::Edit);
+        assert_eq!(editor.file_name, None);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_quit_after_saving_an_unnamed_buffer() {
+        // Given
+        let mut editor = Editor::from(vec![String::new()]);
+        handle_key_press(Ok(Key::Char('a')), &mut editor, 50);
+        handle_key_press(Ok(Key::Esc), &mut editor, 50);
+        handle_key_press(Ok(Key::Char('y')), &mut editor, 50);
+
+        // When
+        for c in "test_editor_quit_after_save_as.txt".chars() {
+            handle_key_press(Ok(Key::Char(c)), &mut editor, 50);
+        }
+        let result = handle_key_press(Ok(Key::Char('\n')), &mut editor, 50);
+
+        // Then
+        assert!(!result);
+
+        fs::remove_file("test_editor_quit_after_save_as.txt").unwrap();
+    }
+
+    fn type_text(editor: &mut Editor, text: &str) {
+        for c in text.chars() {
+            handle_key_press(Ok(Key::Char(c)), editor, 10);
+        }
+    }
+
+    #[test]
+    fn test_handle_key_press_should_move_to_the_match_while_typing_the_search() {
+        // Given
+        let lines = vec![String::from("first"), String::from("the second line")];
+        let mut editor = Editor::from(lines);
+        handle_key_press(Ok(Key::Ctrl('f')), &mut editor, 10);
+
+        // When
+        type_text(&mut editor, "sec");
+
+        // Then
+        assert_eq!(editor.cursor.x, 5);
+        assert_eq!(editor.cursor.y, 2);
+        assert_eq!(editor.get_search_query(), Some("sec"));
+    }
+
+    #[test]
+    fn test_handle_key_press_should_scroll_to_a_match_out_of_the_screen() {
+        // Given
+        let mut lines: Vec<String> = (0..100).map(|i| format!("line {}", i)).collect();
+        lines.push(String::from("needle"));
+        let mut editor = Editor::from(lines);
+        handle_key_press(Ok(Key::Ctrl('f')), &mut editor, 10);
+
+        // When
+        type_text(&mut editor, "needle");
+
+        // Then
+        assert_eq!(editor.cursor.y_offset, 96);
+        assert_eq!(editor.cursor.y, 5);
+        assert_eq!(editor.cursor.x, 1);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_go_to_the_next_and_previous_match_with_wrap_around() {
+        // Given
+        let lines = vec![String::from("ab ab"), String::from("ab")];
+        let mut editor = Editor::from(lines);
+        handle_key_press(Ok(Key::Ctrl('f')), &mut editor, 10);
+        type_text(&mut editor, "ab");
+
+        // When
+        handle_key_press(Ok(Key::Down), &mut editor, 10);
+        handle_key_press(Ok(Key::Down), &mut editor, 10);
+        let (x, y) = (editor.cursor.x, editor.cursor.y);
+        handle_key_press(Ok(Key::Down), &mut editor, 10);
+
+        // Then
+        assert_eq!((x, y), (1, 2));
+        assert_eq!((editor.cursor.x, editor.cursor.y), (1, 1));
+        handle_key_press(Ok(Key::Up), &mut editor, 10);
+        assert_eq!((editor.cursor.x, editor.cursor.y), (1, 2));
+    }
+
+    #[test]
+    fn test_handle_key_press_should_restore_the_cursor_when_the_search_is_cancelled() {
+        // Given
+        let lines = vec![String::from("first"), String::from("second")];
+        let mut editor = Editor::from(lines);
+        editor.cursor.x = 3;
+        handle_key_press(Ok(Key::Ctrl('f')), &mut editor, 10);
+        type_text(&mut editor, "second");
+
+        // When
+        handle_key_press(Ok(Key::Esc), &mut editor, 10);
+
+        // Then
+        assert_eq!(editor.mode, Mode::Edit);
+        assert_eq!((editor.cursor.x, editor.cursor.y), (3, 1));
+    }
+
+    #[test]
+    fn test_handle_key_press_should_report_a_failing_search() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("text")]);
+        handle_key_press(Ok(Key::Ctrl('f')), &mut editor, 10);
+
+        // When
+        type_text(&mut editor, "z");
+
+        // Then
+        match editor.mode {
+            Mode::Search { minibuffer, .. } => assert_eq!(minibuffer.label, FAILING_SEARCH_LABEL),
+            _ => panic!("the search should still be open"),
+        }
+    }
+
+    fn start_replace_with_keys(editor: &mut Editor, pattern: &str, template: &str) {
+        handle_key_press(Ok(Key::Ctrl('r')), editor, 10);
+        type_text(editor, pattern);
+        type_text(editor, "\n");
+        type_text(editor, template);
+        type_text(editor, "\n");
+    }
+
+    #[test]
+    fn test_handle_key_press_should_replace_all_matches_with_capture_groups() {
+        // Given
+        let lines = vec![String::from("x = 1, y = 22"), String::from("z = 3")];
+        let mut editor = Editor::from(lines);
+        start_replace_with_keys(&mut editor, r"(\w) = (\d+)", "$2 =: $1");
+
+        // When
+        handle_key_press(Ok(Key::Char('a')), &mut editor, 10);
+
+        // Then
+        assert_eq!(editor.get_text(), "1 =: x, 22 =: y\n3 =: z");
+        assert_eq!(editor.mode, Mode::Edit);
+        assert_eq!(editor.message, Some(String::from("Replaced 3 occurrences")));
+    }
+
+    #[test]
+    fn test_handle_key_press_should_confirm_each_replacement() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("cat cat cat")]);
+        start_replace_with_keys(&mut editor, "cat", "dog");
+
+        // When
+        handle_key_press(Ok(Key::Char('y')), &mut editor, 10);
+        handle_key_press(Ok(Key::Char('n')), &mut editor, 10);
+        handle_key_press(Ok(Key::Char('y')), &mut editor, 10);
+
+        // Then
+        assert_eq!(editor.get_text(), "dog cat dog");
+        assert_eq!(editor.mode, Mode::Edit);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_replace_only_one_match() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("cat cat")]);
+        start_replace_with_keys(&mut editor, "cat", "dog");
+
+        // When
+        handle_key_press(Ok(Key::Char('.')), &mut editor, 10);
+
+        // Then
+        assert_eq!(editor.get_text(), "dog cat");
+        assert_eq!(editor.message, Some(String::from("Replaced 1 occurrence")));
+    }
+
+    #[test]
+    fn test_handle_key_press_should_undo_a_replace_session_in_one_step() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("cat cat"), String::from("cat")]);
+        handle_key_press(Ok(Key::Char('>')), &mut editor, 10);
+        start_replace_with_keys(&mut editor, "cat", "a dog");
+        handle_key_press(Ok(Key::Char('y')), &mut editor, 10);
+        handle_key_press(Ok(Key::Char('a')), &mut editor, 10);
+        assert_eq!(editor.get_text(), ">a dog a dog\na dog");
+
+        // When
+        handle_key_press(Ok(Key::Ctrl('z')), &mut editor, 10);
+
+        // Then
+        assert_eq!(editor.get_text(), ">cat cat\ncat");
+    }
+
+    #[test]
+    fn test_handle_key_press_should_report_an_invalid_regular_expression() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("text")]);
+        handle_key_press(Ok(Key::Ctrl('r')), &mut editor, 10);
+        type_text(&mut editor, "(unclosed");
+
+        // When
+        handle_key_press(Ok(Key::Char('\n')), &mut editor, 10);
+
+        // Then
+        assert_eq!(editor.mode, Mode::Edit);
+        assert!(editor
+            .message
+            .unwrap()
+            .starts_with("Invalid regular expression"));
+    }
+
+    fn press_keys(editor: &mut Editor, keys: &[Key]) {
+        for key in keys {
+            handle_key_press(Ok(*key), editor, 10);
+        }
+    }
+
+    #[test]
+    fn test_handle_key_press_should_select_with_shift_and_cut_and_paste() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("hello world")]);
+        press_keys(&mut editor, &[Key::ShiftRight; 6]);
+        assert_eq!(editor.get_selection_range(), Some(0..6));
+
+        // When
+        press_keys(&mut editor, &[Key::Ctrl('x'), Key::F(2), Key::Ctrl('v')]);
+
+        // Then
+        assert_eq!(editor.get_text(), "worldhello ");
+        assert_eq!(editor.selection, None);
+        assert_eq!(editor.cursor.x, 12);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_select_across_lines_with_the_mark() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("abc"), String::from("def")]);
+        press_keys(&mut editor, &[Key::Right, Key::Null, Key::Down]);
+
+        // When
+        press_keys(
+            &mut editor,
+            &[Key::Ctrl('c'), Key::F(4), Key::F(2), Key::Ctrl('v')],
+        );
+
+        // Then
+        assert_eq!(editor.get_all_lines(), vec!["abc", "defbc", "d"]);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_clear_a_shift_selection_on_plain_move() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("abc")]);
+        press_keys(&mut editor, &[Key::ShiftRight]);
+
+        // When
+        press_keys(&mut editor, &[Key::Right]);
+
+        // Then
+        assert_eq!(editor.selection, None);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_delete_the_selection_in_one_undo_step() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("one"), String::from("two")]);
+        press_keys(&mut editor, &[Key::Right, Key::ShiftDown, Key::ShiftRight]);
+
+        // When
+        press_keys(&mut editor, &[Key::Backspace]);
+
+        // Then
+        assert_eq!(editor.get_text(), "oo");
+        assert_eq!((editor.cursor.x, editor.cursor.y), (2, 1));
+        press_keys(&mut editor, &[Key::Ctrl('z')]);
+        assert_eq!(editor.get_text(), "one\ntwo");
+    }
+
+    #[test]
+    fn test_handle_key_press_should_replace_the_selection_when_typing() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("abc")]);
+        press_keys(&mut editor, &[Key::ShiftRight, Key::ShiftRight]);
+
+        // When
+        type_text(&mut editor, "x");
+
+        // Then
+        assert_eq!(editor.get_text(), "xc");
+    }
+
+    #[test]
+    fn test_handle_key_press_should_clear_the_selection_instead_of_quitting() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("abc")]);
+        press_keys(&mut editor, &[Key::Null]);
+
+        // When
+        let should_continue = handle_key_press(Ok(Key::Esc), &mut editor, 10);
+
+        // Then
+        assert!(should_continue);
+        assert_eq!(editor.selection, None);
+    }
+
+    #[test]
+    fn test_get_selection_in_line_should_clip_the_selection_to_the_line() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("abc"), String::from("def")]);
+        press_keys(&mut editor, &[Key::Right, Key::ShiftDown]);
+
+        // When
+        let first_line = editor.get_selection_in_line(0, 3);
+        let second_line = editor.get_selection_in_line(1, 3);
+
+        // Then
+        assert_eq!(first_line, Some(1..3));
+        assert_eq!(second_line, Some(0..1));
+    }
+
+    #[test]
+    fn test_handle_key_press_should_paste_from_the_paste_command() {
+        // Given
+        let config = Config::parse("paste_command = printf pasted");
+        let mut editor = Editor::from(vec![String::from("abc")]).with_config(config);
+        press_keys(&mut editor, &[Key::ShiftRight]);
+
+        // When
+        press_keys(&mut editor, &[Key::Ctrl('v')]);
+
+        // Then
+        assert_eq!(editor.get_text(), "pastedbc");
+    }
+
+    #[test]
+    fn test_handle_key_press_should_report_a_failing_copy_command() {
+        // Given
+        let config = Config::parse("copy_command = false");
+        let mut editor = Editor::from(vec![String::from("abc")]).with_config(config);
+        press_keys(&mut editor, &[Key::ShiftRight]);
+
+        // When
+        press_keys(&mut editor, &[Key::Ctrl('c')]);
+
+        // Then
+        assert!(editor.message.unwrap().starts_with("Clipboard error"));
+    }
+
+    #[test]
+    fn test_handle_paste_should_insert_the_text_in_a_single_undo_step() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("ab")]);
+        press_keys(&mut editor, &[Key::Right]);
+
+        // When
+        handle_paste("one\ntwo\n", &mut editor, 10);
+
+        // Then
+        assert_eq!(editor.get_all_lines(), vec!["aone", "two", "b"]);
+        assert_eq!((editor.cursor.x, editor.cursor.y), (1, 3));
+        press_keys(&mut editor, &[Key::Ctrl('z')]);
+        assert_eq!(editor.get_text(), "ab");
+    }
+
+    #[test]
+    fn test_handle_paste_should_type_the_first_line_in_a_prompt() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("ab")]);
+        press_keys(&mut editor, &[Key::Ctrl('f')]);
 
-        let mut editor = Editor {
-            piece_table,
-            cursor,
-        };
+        // When
+        handle_paste("b\nignored", &mut editor, 10);
+
+        // Then
+        assert_eq!(editor.get_search_query(), Some("b"));
+        assert_eq!(editor.cursor.x, 2);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_move_by_words_across_lines() {
+        // Given
+        let mut editor = Editor::from(vec![
+            String::from("fn main() {"),
+            String::from("    "),
+            String::from("    let x = 1;"),
+        ]);
 
         // When
-        editor.remove(36);
+        press_keys(&mut editor, &[Key::CtrlRight; 2]);
+        let first_positions = (editor.cursor.x, editor.cursor.y);
+        press_keys(&mut editor, &[Key::CtrlRight; 3]);
+        let second_positions = (editor.cursor.x, editor.cursor.y);
+        press_keys(&mut editor, &[Key::CtrlLeft; 2]);
 
         // Then
-        assert_eq!(editor.cursor.x, 15);
-        assert_eq!(editor.cursor.y, 1);
-        assert_eq!(editor.cursor.y_offset, 0);
-        assert_eq!(
-            editor.get_editor_lines(36),
-            vec!["this is a test", "this is a test2"]
-        );
+        assert_eq!(first_positions, (8, 1));
+        assert_eq!(second_positions, (8, 3));
+        assert_eq!((editor.cursor.x, editor.cursor.y), (11, 1));
     }
 
     #[test]
-    fn remove_should_remove_not_empty_line() {
+    fn test_handle_key_press_should_scroll_when_moving_by_words() {
         // Given
-        let cursor = CursorPosition {
-            x: 1,
-            y: 2,
-            y_offset: 0,
-        };
-        let lines = vec![
-            String::from("this is a test"),
-            String::from("this is a test2"),
-            String::from("this is a test3"),
-        ];
-        let piece_table = PieceTable::new(lines.clone().join("\n"));
+        let lines = (0..20).map(|i| format!("word{}", i)).collect();
+        let mut editor = Editor::from(lines);
 
-        let mut editor = Editor {
-            piece_table,
-            cursor,
-        };
+        // When
+        press_keys(&mut editor, &[Key::CtrlRight; 10]);
+
+        // Then
+        assert_eq!(editor.cursor.y_offset, 1);
+        assert_eq!(editor.cursor.y, 9);
+        assert_eq!(editor.get_current_line(), "word9");
+        press_keys(&mut editor, &[Key::F(1), Key::CtrlLeft]);
+        assert_eq!(editor.cursor.y_offset, 1);
+        assert_eq!((editor.cursor.x, editor.cursor.y), (1, 8));
+    }
+
+    #[test]
+    fn test_handle_key_press_should_delete_words() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("one two"), String::from("three")]);
+        press_keys(&mut editor, &[Key::Down, Key::CtrlRight]);
 
         // When
-        editor.remove(36);
+        press_keys(&mut editor, &[Key::Ctrl('w'), Key::Alt('\x7f')]);
+        let after_backward = editor.get_text();
+        press_keys(&mut editor, &[Key::F(3), Key::Alt('d')]);
 
         // Then
-        assert_eq!(
-            editor.get_editor_lines(36),
-            vec!["this is a testthis is a test2", "this is a test3"]
-        );
-        assert_eq!(editor.cursor.x, 15);
-        assert_eq!(editor.cursor.y, 1);
-        assert_eq!(editor.cursor.y_offset, 0);
+        assert_eq!(after_backward, "one ");
+        assert_eq!(editor.get_text(), " ");
+        press_keys(&mut editor, &[Key::Ctrl('z')]);
+        assert_eq!(editor.get_text(), "one ");
     }
 
     #[test]
-    fn remove_should_not_remove() {
+    fn test_handle_key_press_should_delete_a_single_character_with_ctrl_h() {
         // Given
-        let cursor = CursorPosition {
-            x: 1,
-            y: 1,
-            y_offset: 0,
-        };
-        let lines = vec![
-            String::from("this is a test"),
-            String::from("this is a test2"),
-        ];
-        let piece_table = PieceTable::new(lines.clone().join("\n"));
+        let mut editor = Editor::from(vec![String::from("one two")]);
+        press_keys(&mut editor, &[Key::F(2)]);
 
-        let mut editor = Editor {
-            piece_table,
-            cursor,
-        };
+        // When
+        press_keys(&mut editor, &[Key::Ctrl('h')]);
+
+        // Then
+        assert_eq!(editor.get_text(), "one tw");
+    }
+
+    #[test]
+    fn test_handle_key_press_should_move_by_pages_and_keep_the_column() {
+        // Given
+        let lines = (0..30)
+            .map(|i| match i % 2 {
+                0 => String::from("\tlong line"),
+                _ => String::from("another long line"),
+            })
+            .collect();
+        let mut editor = Editor::from(lines);
+        press_keys(&mut editor, &[Key::End]);
 
         // When
-        editor.remove(36);
+        press_keys(&mut editor, &[Key::PageDown]);
+        let after_page_down = (editor.cursor.x, editor.cursor.y, editor.cursor.y_offset);
+        press_keys(&mut editor, &[Key::PageDown, Key::PageDown, Key::PageDown]);
+        let at_the_end = (editor.cursor.x, editor.cursor.y, editor.cursor.y_offset);
+        press_keys(&mut editor, &[Key::PageUp]);
 
         // Then
+        assert_eq!(after_page_down, (14, 1, 9));
+        assert_eq!(at_the_end, (14, 9, 21));
         assert_eq!(
-            editor.get_editor_lines(36),
-            vec!["this is a test", "this is a test2"]
+            (editor.cursor.x, editor.cursor.y, editor.cursor.y_offset),
+            (11, 9, 12)
         );
-        assert_eq!(editor.cursor.x, 1);
-        assert_eq!(editor.cursor.y, 1);
-        assert_eq!(editor.cursor.y_offset, 0);
     }
 
     #[test]
-    fn remove_should_remove_char_with_offset() {
+    fn test_handle_key_press_should_jump_to_the_ends_of_lines_and_file() {
         // Given
-        let cursor = CursorPosition {
-            x: 5,
-            y: 3,
-            y_offset: 1,
-        };
-        let lines = vec![
-            String::from("this is a test"),
-            String::from("this is a test2"),
-            String::from("this is a test3"),
-            String::from("this is a test4"),
-        ];
-        let piece_table = PieceTable::new(lines.clone().join("\n"));
+        let mut editor = Editor::from(vec![String::from("first"), String::from("last")]);
 
-        let mut editor = Editor {
-            piece_table,
-            cursor,
-        };
+        // When
+        press_keys(&mut editor, &[Key::CtrlEnd]);
+        let at_the_end = (editor.cursor.x, editor.cursor.y);
+        press_keys(&mut editor, &[Key::Home]);
+        let at_line_start = (editor.cursor.x, editor.cursor.y);
+        press_keys(&mut editor, &[Key::CtrlHome, Key::End]);
+
+        // Then
+        assert_eq!(at_the_end, (5, 2));
+        assert_eq!(at_line_start, (1, 2));
+        assert_eq!((editor.cursor.x, editor.cursor.y), (6, 1));
+    }
+
+    #[test]
+    fn test_handle_key_press_should_keep_the_column_through_short_lines() {
+        // Given
+        let mut editor = Editor::from(vec![
+            String::from("a long line"),
+            String::from("ab"),
+            String::from(""),
+            String::from("\tlonger line"),
+        ]);
+        press_keys(&mut editor, &[Key::End]);
 
         // When
-        editor.remove(3);
+        press_keys(&mut editor, &[Key::Down, Key::Down]);
+        let on_the_empty_line = editor.cursor.x;
+        press_keys(&mut editor, &[Key::Down]);
+        let on_the_tabbed_line = editor.cursor.x;
+        press_keys(&mut editor, &[Key::Up, Key::Up, Key::Up]);
 
         // Then
-        assert_eq!(
-            editor.get_range_lines(0, 4),
-            vec![
-                "this is a test",
-                "this is a test2",
-                "this is a test3",
-                "thi is a test4"
-            ]
-        );
+        assert_eq!(on_the_empty_line, 1);
+        assert_eq!(on_the_tabbed_line, 9);
+        assert_eq!(editor.cursor.x, 12);
+    }
+
+    #[test]
+    fn test_handle_key_press_should_reset_the_column_on_horizontal_moves_and_edits() {
+        // Given
+        let mut editor = Editor::from(vec![
+            String::from("a long line"),
+            String::from("ab"),
+            String::from("another line"),
+        ]);
+        press_keys(&mut editor, &[Key::End, Key::Down]);
+
+        // When
+        press_keys(&mut editor, &[Key::Left, Key::Down]);
+        let after_move = editor.cursor.x;
+        press_keys(&mut editor, &[Key::Up, Key::End, Key::Up, Key::Down]);
+        type_text(&mut editor, "c");
+        press_keys(&mut editor, &[Key::Down]);
+
+        // Then
+        assert_eq!(after_move, 2);
+        assert_eq!(editor.cursor.preferred_column, Some(3));
         assert_eq!(editor.cursor.x, 4);
-        assert_eq!(editor.cursor.y, 3);
-        assert_eq!(editor.cursor.y_offset, 1);
     }
 
     #[test]
-    fn remove_should_remove_char_with_offset_on_first_line() {
+    fn test_handle_key_press_should_go_to_a_line_and_column() {
         // Given
-        let cursor = CursorPosition {
-            x: 5,
-            y: 1,
-            y_offset: 1,
-        };
+        let lines = (1..=40).map(|i| format!("line {}", i)).collect();
+        let mut editor = Editor::from(lines);
+        handle_key_press(Ok(Key::Ctrl('g')), &mut editor, 10);
+        type_text(&mut editor, "30:4");
 
-        let lines = vec![
-            String::from("this is a test"),
-            String::from("this is a test2"),
-            String::from("this is a test3"),
-            String::from("this is a test4"),
-        ];
-        let piece_table = PieceTable::new(lines.clone().join("\n"));
+        // When
+        handle_key_press(Ok(Key::Char('\n')), &mut editor, 10);
 
-        let mut editor = Editor {
-            piece_table,
-            cursor,
-        };
+        // Then
+        assert_eq!(editor.mode, Mode::Edit);
+        assert_eq!(editor.get_current_line(), "line 30");
+        assert_eq!(editor.cursor.x, 4);
+        assert_eq!((editor.cursor.y, editor.cursor.y_offset), (5, 25));
+    }
+
+    #[test]
+    fn test_go_to_position_should_clamp_to_the_text() {
+        // Given
+        let mut editor = Editor::from(vec![String::from("ab"), String::from("cd")]);
 
         // When
-        editor.remove(36);
+        editor.go_to_position(FilePosition { line: 9, column: 9 }, 10);
 
         // Then
         assert_eq!(
-            editor.get_range_lines(0, 4),
-            vec![
-                "this is a test",
-                "thi is a test2",
-                "this is a test3",
-                "this is a test4"
-            ]
+            (editor.cursor.x, editor.cursor.y, editor.cursor.y_offset),
+            (3, 2, 0)
         );
-        assert_eq!(editor.cursor.x, 4);
-        assert_eq!(editor.cursor.y, 1);
-        assert_eq!(editor.cursor.y_offset, 1);
     }
 
     #[test]
-    fn remove_should_remove_line_with_offset_reduced() {
+    fn test_handle_key_press_should_report_an_invalid_line_number() {
         // Given
-        let cursor = CursorPosition {
-            x: 1,
-            y: 1,
-            y_offset: 1,
-        };
-        let lines = vec![
-            String::from("this is a test"),
-            String::from("this is a test2"),
-            String::from("this is a test3"),
-            String::from("this is a test4"),
-        ];
-        let piece_table = PieceTable::new(lines.clone().join("\n"));
-
-        let mut editor = Editor {
-            piece_table,
-            cursor,
-        };
+        let mut editor = Editor::from(vec![String::from("ab")]);
+        handle_key_press(Ok(Key::Ctrl('g')), &mut editor, 10);
+        type_text(&mut editor, "top");
 
         // When
-        editor.remove(5);
+        handle_key_press(Ok(Key::Char('\n')), &mut editor, 10);
 
         // Then
         assert_eq!(
-            editor.get_range_lines(0, 4),
-            vec![
-                "this is a testthis is a test2",
-                "this is a test3",
-                "this is a test4"
-            ]
+            editor.message,
+            Some(String::from("Invalid line number: top"))
         );
-        assert_eq!(editor.cursor.x, 15);
-        assert_eq!(editor.cursor.y, 1);
-        assert_eq!(editor.cursor.y_offset, 0);
     }
 
     #[test]
-    fn remove_should_remove_last_empty_line() {
+    fn test_handle_key_press_should_refuse_to_edit_a_readonly_file() {
         // Given
-        let cursor = CursorPosition {
-            x: 1,
-            y: 4,
-            y_offset: 0,
-        };
-        let lines = vec![
-            String::from("this is a test"),
-            String::from(""),
-            String::from(""),
-            String::from(""),
-        ];
-        let piece_table = PieceTable::new(lines.clone().join("\n"));
-
-        let mut editor = Editor {
-            piece_table,
-            cursor,
-        };
+        let mut editor = Editor::from(vec![String::from("abc")]).with_readonly(true);
 
         // When
-        editor.remove(5);
+        type_text(&mut editor, "x");
+        handle_paste("pasted", &mut editor, 10);
+        press_keys(&mut editor, &[Key::ShiftRight, Key::Ctrl('x'), Key::Right]);
 
         // Then
-        assert_eq!(editor.get_range_lines(0, 4), vec!["this is a test", "", ""]);
-        assert_eq!(editor.cursor.x, 1);
-        assert_eq!(editor.cursor.y, 3);
-        assert_eq!(editor.cursor.y_offset, 0);
+        assert_eq!(editor.get_text(), "abc");
+        assert_eq!(editor.message, None);
+        assert_eq!(editor.cursor.x, 3);
+        press_keys(&mut editor, &[Key::Backspace]);
+        assert_eq!(editor.message, Some(String::from(READONLY_MESSAGE)));
     }
 
     #[test]
-    fn get_cursor_position_in_file_should_compute_nb_of_characters() {
+    fn test_handle_key_press_should_not_write_a_readonly_file() {
         // Given
-        let cursor = CursorPosition {
-            x: 1,
-            y: 2,
-            y_offset: 0,
-        };
-        let lines = vec![
-            String::from("this is a test"),
-            String::from("this is a test2"),
-            String::from("this is a test3"),
-        ];
-        let piece_table = PieceTable::new(lines.clone().join("\n"));
-
-        let editor = Editor {
-            piece_table,
-            cursor,
-        };
+        let file_name = String::from("test_editor_readonly_file.txt");
+        fs::write(&file_name, "a\n").unwrap();
+        let mut editor = Editor::from(vec![String::from("a")])
+            .with_file_name(Some(file_name.clone()))
+            .with_readonly(true);
 
         // When
-        let result = editor.get_cursor_position_in_file();
+        press_keys(&mut editor, &[Key::Alt('l'), Key::Ctrl('s')]);
 
         // Then
-        assert_eq!(result, 15);
+        assert_eq!(editor.file_format.line_ending, LineEnding::LF);
+        assert_eq!(editor.message, Some(String::from(READONLY_MESSAGE)));
+        assert_eq!(fs::read(&file_name).unwrap(), b"a\n");
+
+        fs::remove_file(&file_name).unwrap();
     }
 
     #[test]
-    fn get_cursor_position_in_file_should_compute_nb_of_characters_with_offset() {
+    fn test_save_as_should_write_a_readonly_buffer_to_another_file() {
         // Given
-        let cursor = CursorPosition {
-            x: 5,
-            y: 3,
-            y_offset: 1,
-        };
-        let lines = vec![
-            String::from("this is a test"),
-            String::from("this is a test2"),
-            String::from("this is a test3"),
-            String::from("this is a test4"),
-        ];
-        let piece_table = PieceTable::new(lines.clone().join("\n"));
-
-        let editor = Editor {
-            piece_table,
-            cursor,
-        };
+        let file_name = String::from("test_editor_readonly_copy.txt");
+        let mut editor = Editor::from(vec![String::from("a")])
+            .with_file_name(Some(String::from("test_editor_readonly_source.txt")))
+            .with_readonly(true);
 
         // When
-        let result = editor.get_cursor_position_in_file();
+        editor.save_as(file_name.clone());
 
         // Then
-        assert_eq!(result, 51);
+        assert_eq!(fs::read_to_string(&file_name).unwrap(), "a\n");
+
+        fs::remove_file(&file_name).unwrap();
     }
 }
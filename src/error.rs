@@ -0,0 +1,76 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum RustorError {
+    Load {
+        file_name: String,
+        source: io::Error,
+    },
+    Save {
+        file_name: String,
+        source: io::Error,
+    },
+    Terminal(io::Error),
+    Clipboard(io::Error),
+    Signal(i32),
+    Panic,
+    Usage(String),
+}
+
+impl fmt::Display for RustorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RustorError::Load { file_name, source } => {
+                write!(f, "Could not open {}: {}", file_name, source)
+            }
+            RustorError::Save { file_name, source } => {
+                write!(f, "Could not save {}: {}", file_name, source)
+            }
+            RustorError::Terminal(source) => write!(f, "Terminal error: {}", source),
+            RustorError::Clipboard(source) => write!(f, "Clipboard error: {}", source),
+            RustorError::Signal(signal) => write!(f, "Terminated by signal {}", signal),
+            RustorError::Panic => write!(f, "Rustor crashed"),
+            RustorError::Usage(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl error::Error for RustorError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RustorError::Load { source, .. } => Some(source),
+            RustorError::Save { source, .. } => Some(source),
+            RustorError::Terminal(source) => Some(source),
+            RustorError::Clipboard(source) => Some(source),
+            RustorError::Signal(_) | RustorError::Panic | RustorError::Usage(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for RustorError {
+    fn from(error: io::Error) -> RustorError {
+        RustorError::Terminal(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_should_name_the_file_that_failed_to_load() {
+        // Given
+        let error = RustorError::Load {
+            file_name: String::from("notes.txt"),
+            source: io::Error::from(io::ErrorKind::PermissionDenied),
+        };
+
+        // When
+        let result = error.to_string();
+
+        // Then
+        assert_eq!(result, "Could not open notes.txt: permission denied");
+    }
+}
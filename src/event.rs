@@ -0,0 +1,138 @@
+use std::io::{self, stdin, Read};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use signal_hook::consts::{SIGHUP, SIGTERM};
+use signal_hook::iterator::Signals;
+use termion::event::{self, Key};
+use termion::input::TermReadEventsAndRaw;
+
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+// termion does not parse modified Delete keys, so Ctrl-Delete is read as Alt-d.
+const CTRL_DELETE: &[u8] = b"\x1b[3;5~";
+
+pub enum Event {
+    Key(Result<Key, io::Error>),
+    Paste(String),
+    Signal(i32),
+}
+
+fn decode_paste(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+}
+
+fn read_events<R: Read>(input: R, sender: &Sender<Event>) {
+    let mut paste: Option<Vec<u8>> = None;
+    for result in input.events_and_raw() {
+        let event = match (result, paste.as_mut()) {
+            (Ok((_, raw)), None) if raw == PASTE_START => {
+                paste = Some(vec![]);
+                continue;
+            }
+            (Ok((_, raw)), Some(bytes)) if raw == PASTE_END => {
+                let text = decode_paste(bytes);
+                paste = None;
+                Event::Paste(text)
+            }
+            (Ok((_, raw)), Some(bytes)) => {
+                bytes.extend(raw);
+                continue;
+            }
+            (Ok((_, raw)), None) if raw == CTRL_DELETE => Event::Key(Ok(Key::Alt('d'))),
+            (Ok((event::Event::Key(key), _)), None) => Event::Key(Ok(key)),
+            (Ok(_), None) => continue,
+            (Err(error), _) => Event::Key(Err(error)),
+        };
+        if sender.send(event).is_err() {
+            break;
+        }
+    }
+}
+
+fn open_input() -> io::Result<Box<dyn Read + Send>> {
+    if termion::is_tty(&stdin()) {
+        Ok(Box::new(stdin()))
+    } else {
+        // The document was piped on stdin, the keys come from the terminal.
+        Ok(Box::new(termion::get_tty()?))
+    }
+}
+
+fn spawn_input_reader(sender: Sender<Event>) -> io::Result<()> {
+    let input = open_input()?;
+    thread::spawn(move || read_events(input, &sender));
+    Ok(())
+}
+
+fn spawn_signal_listener(sender: Sender<Event>) -> io::Result<()> {
+    let mut signals = Signals::new([SIGTERM, SIGHUP])?;
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            if sender.send(Event::Signal(signal)).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+pub fn listen_events() -> io::Result<Receiver<Event>> {
+    let (sender, receiver) = mpsc::channel();
+    spawn_signal_listener(sender.clone())?;
+    spawn_input_reader(sender)?;
+    Ok(receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_events(input: &[u8]) -> Vec<Event> {
+        let (sender, receiver) = mpsc::channel();
+        read_events(input, &sender);
+        drop(sender);
+        receiver.iter().collect()
+    }
+
+    #[test]
+    fn read_events_should_deliver_a_bracketed_paste_as_one_event() {
+        // Given
+        let input = "a\x1b[200~x\r\n\x1bé\ty\x1b[201~b".as_bytes();
+
+        // When
+        let events = collect_events(input);
+
+        // Then
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], Event::Key(Ok(Key::Char('a')))));
+        assert!(matches!(&events[1], Event::Paste(text) if text == "x\n\x1bé\ty"));
+        assert!(matches!(events[2], Event::Key(Ok(Key::Char('b')))));
+    }
+
+    #[test]
+    fn decode_paste_should_normalize_carriage_returns() {
+        // Given
+        let bytes = b"one\r\ntwo\rthree\n";
+
+        // When
+        let result = decode_paste(bytes);
+
+        // Then
+        assert_eq!(result, "one\ntwo\nthree\n");
+    }
+
+    #[test]
+    fn read_events_should_read_ctrl_delete_as_alt_d() {
+        // Given
+        let input = b"\x1b[3;5~";
+
+        // When
+        let events = collect_events(input);
+
+        // Then
+        assert!(matches!(events[..], [Event::Key(Ok(Key::Alt('d')))]));
+    }
+}
@@ -1,43 +1,341 @@
-use std::fs::File;
+use std::convert::TryFrom;
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io;
 use std::io::prelude::*;
-use std::io::{self, BufRead};
-use std::path::Path;
-
-fn read_lines<P>(file_name: P) -> io::Result<io::Lines<io::BufReader<File>>>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(file_name)?;
-    Ok(io::BufReader::new(file).lines())
-}
-
-pub fn init_lines(file_name_option: Option<&String>) -> Vec<String> {
-    let mut lines: Vec<String> = vec![];
-    if let Some(file_name) = file_name_option {
-        if let Ok(lines_in_file) = read_lines(file_name) {
-            for line_in_file in lines_in_file {
-                if let Ok(line) = line_in_file {
-                    lines.push(line);
-                }
-            }
+use std::path::{Path, PathBuf};
+use std::process;
+
+use error::RustorError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum LineEnding {
+    LF,
+    CRLF,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileFormat {
+    pub line_ending: LineEnding,
+    pub has_final_newline: bool,
+    pub encoding: Encoding,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::LF => "\n",
+            LineEnding::CRLF => "\r\n",
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            LineEnding::LF => "LF",
+            LineEnding::CRLF => "CRLF",
+        }
+    }
+
+    pub fn toggle(&self) -> LineEnding {
+        match self {
+            LineEnding::LF => LineEnding::CRLF,
+            LineEnding::CRLF => LineEnding::LF,
+        }
+    }
+}
+
+impl Encoding {
+    pub fn parse(name: &str) -> Option<Encoding> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "iso-8859-1" | "latin-1" | "latin1" => Some(Encoding::Latin1),
+            _ => None,
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Latin1 => "ISO-8859-1",
         }
     }
-    if lines.is_empty() {
-        lines.push(String::new());
+
+    fn decode(&self, bytes: Vec<u8>) -> io::Result<String> {
+        match self {
+            Encoding::Utf8 => String::from_utf8(bytes).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                )
+            }),
+            Encoding::Latin1 => Ok(bytes.into_iter().map(char::from).collect()),
+        }
+    }
+
+    fn encode(&self, text: String) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Utf8 => Ok(text.into_bytes()),
+            Encoding::Latin1 => text
+                .chars()
+                .map(|c| {
+                    u8::try_from(c).map_err(|_| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("{:?} cannot be encoded in {}", c, self.get_name()),
+                        )
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl FileFormat {
+    pub fn new() -> FileFormat {
+        FileFormat {
+            line_ending: LineEnding::LF,
+            has_final_newline: true,
+            encoding: Encoding::Utf8,
+        }
+    }
+
+    fn detect(content: &str, encoding: Encoding) -> FileFormat {
+        let number_of_line_breaks = content.matches('\n').count();
+        let number_of_crlf = content.matches("\r\n").count();
+        let line_ending = if number_of_crlf * 2 > number_of_line_breaks {
+            LineEnding::CRLF
+        } else {
+            LineEnding::LF
+        };
+        FileFormat {
+            line_ending,
+            has_final_newline: content.ends_with('\n'),
+            encoding,
+        }
+    }
+}
+
+fn split_lines(content: &str, file_format: &FileFormat) -> Vec<String> {
+    let content = content.strip_suffix('\n').unwrap_or(content);
+    content
+        .split('\n')
+        .map(|line| match file_format.line_ending {
+            LineEnding::CRLF => line.strip_suffix('\r').unwrap_or(line),
+            LineEnding::LF => line,
+        })
+        .map(String::from)
+        .collect()
+}
+
+fn get_empty_lines(encoding: Encoding) -> (Vec<String>, FileFormat) {
+    let file_format = FileFormat {
+        encoding,
+        ..FileFormat::new()
+    };
+    (vec![String::new()], file_format)
+}
+
+fn parse_content(bytes: Vec<u8>, encoding: Encoding) -> io::Result<(Vec<String>, FileFormat)> {
+    let content = encoding.decode(bytes)?;
+    let file_format = FileFormat::detect(&content, encoding);
+    Ok((split_lines(&content, &file_format), file_format))
+}
+
+pub fn init_lines(
+    file_name_option: Option<&String>,
+    encoding: Encoding,
+) -> Result<(Vec<String>, FileFormat), RustorError> {
+    let file_name = match file_name_option {
+        Some(file_name) => file_name,
+        None => return Ok(get_empty_lines(encoding)),
+    };
+    match fs::read(file_name).and_then(|bytes| parse_content(bytes, encoding)) {
+        Ok(result) => Ok(result),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(get_empty_lines(encoding)),
+        Err(error) => Err(RustorError::Load {
+            file_name: file_name.clone(),
+            source: error,
+        }),
+    }
+}
+
+pub fn read_lines<R: Read>(
+    mut input: R,
+    encoding: Encoding,
+) -> Result<(Vec<String>, FileFormat), RustorError> {
+    let mut bytes = vec![];
+    input
+        .read_to_end(&mut bytes)
+        .and_then(|_| parse_content(bytes, encoding))
+        .map_err(|error| RustorError::Load {
+            file_name: String::from("standard input"),
+            source: error,
+        })
+}
+
+pub fn format_text(text: &str, file_format: &FileFormat) -> String {
+    let mut formatted_text = text.replace('\n', file_format.line_ending.as_str());
+    if file_format.has_final_newline {
+        formatted_text.push_str(file_format.line_ending.as_str());
+    }
+    formatted_text
+}
+
+fn get_sibling_path(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}{}{}", prefix, file_name, suffix))
+}
+
+fn get_temporary_path(path: &Path) -> PathBuf {
+    get_sibling_path(path, ".", &format!(".rustor-{}.tmp", process::id()))
+}
+
+fn get_backup_path(path: &Path) -> PathBuf {
+    get_sibling_path(path, "", "~")
+}
+
+fn get_emergency_path(file_name_option: Option<&String>) -> PathBuf {
+    match file_name_option {
+        Some(file_name) => get_sibling_path(Path::new(file_name), "", ".rustor-emergency"),
+        None => PathBuf::from(format!("rustor-emergency-{}.txt", process::id())),
+    }
+}
+
+#[cfg(unix)]
+fn copy_ownership(path: &Path, metadata: &Metadata) {
+    use std::os::unix::fs::{chown, MetadataExt};
+    // Only root can give a file away, the group is kept when the user belongs to it.
+    if chown(path, Some(metadata.uid()), Some(metadata.gid())).is_err() {
+        let _ = chown(path, None, Some(metadata.gid()));
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_path: &Path, _metadata: &Metadata) {}
+
+fn write_temporary_file(
+    temporary_path: &Path,
+    content: &[u8],
+    metadata: Option<&Metadata>,
+) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(temporary_path)?;
+    file.write_all(content)?;
+    if let Some(metadata) = metadata {
+        file.set_permissions(metadata.permissions())?;
+        copy_ownership(temporary_path, metadata);
+    }
+    file.sync_all()
+}
+
+#[cfg(unix)]
+fn has_hard_links(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() > 1
+}
+
+#[cfg(not(unix))]
+fn has_hard_links(_metadata: &Metadata) -> bool {
+    false
+}
+
+fn check_writable(path: &Path, metadata: &Metadata) -> io::Result<()> {
+    if metadata.permissions().readonly() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the file is read-only",
+        ));
+    }
+    OpenOptions::new().write(true).open(path).map(|_| ())
+}
+
+// Renaming would give the other names of a hard-linked file the old content.
+fn write_in_place(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).truncate(true).open(path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+fn sync_directory(path: &Path) {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if let Ok(directory) = File::open(directory) {
+        let _ = directory.sync_all();
     }
-    lines
 }
 
-pub fn save_to_file(file_name: &str, text: String) -> std::io::Result<()> {
-    let mut file = File::create(file_name)?;
-    file.write_all(text.as_bytes())?;
+pub fn save_to_file(
+    file_name: &str,
+    text: String,
+    file_format: &FileFormat,
+    should_backup: bool,
+) -> Result<(), RustorError> {
+    let path = fs::canonicalize(file_name).unwrap_or_else(|_| PathBuf::from(file_name));
+    let metadata = fs::metadata(&path).ok();
+    let temporary_path = get_temporary_path(&path);
+    let result = file_format
+        .encoding
+        .encode(format_text(&text, file_format))
+        .and_then(|content| {
+            if let Some(metadata) = &metadata {
+                check_writable(&path, metadata)?;
+                if should_backup {
+                    fs::copy(&path, get_backup_path(&path))?;
+                }
+                if has_hard_links(metadata) {
+                    return write_in_place(&path, &content);
+                }
+            }
+            write_temporary_file(&temporary_path, &content, metadata.as_ref())?;
+            fs::rename(&temporary_path, &path)
+        });
+    if let Err(error) = result {
+        let _ = fs::remove_file(&temporary_path);
+        return Err(RustorError::Save {
+            file_name: String::from(file_name),
+            source: error,
+        });
+    }
+    sync_directory(&path);
     Ok(())
 }
 
+pub fn write_emergency_copy(
+    file_name_option: Option<&String>,
+    text: &str,
+    file_format: &FileFormat,
+) -> Result<PathBuf, RustorError> {
+    let path = get_emergency_path(file_name_option);
+    let content = format_text(text, file_format);
+    let content = file_format
+        .encoding
+        .encode(content.clone())
+        .unwrap_or_else(|_| content.into_bytes());
+    match fs::write(&path, content) {
+        Ok(()) => Ok(path),
+        Err(error) => Err(RustorError::Save {
+            file_name: path.to_string_lossy().into_owned(),
+            source: error,
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
 
     #[test]
     fn init_lines_should_return_an_empty_line_when_there_is_no_file() {
@@ -46,7 +344,7 @@ mod tests {
         let expected: Vec<String> = vec![String::new()];
 
         // When
-        let result = init_lines(file_name_option);
+        let (result, _) = init_lines(file_name_option, Encoding::Utf8).unwrap();
 
         // Then
         assert_eq!(expected, result);
@@ -61,11 +359,285 @@ mod tests {
         let expected: Vec<String> = vec![String::new()];
 
         // When
-        let result = init_lines(file_name_option);
+        let (result, _) = init_lines(file_name_option, Encoding::Utf8).unwrap();
 
         // Then
         assert_eq!(expected, result);
 
         fs::remove_file(&file_name).unwrap();
     }
+
+    #[test]
+    fn init_lines_should_detect_crlf_line_endings() {
+        // Given
+        let file_name = String::from("test_file_crlf.txt");
+        fs::write(&file_name, "first\r\nsecond\r\n").unwrap();
+
+        // When
+        let (result, file_format) = init_lines(Some(&file_name), Encoding::Utf8).unwrap();
+
+        // Then
+        assert_eq!(result, vec![String::from("first"), String::from("second")]);
+        assert_eq!(file_format.line_ending, LineEnding::CRLF);
+        assert!(file_format.has_final_newline);
+
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    fn init_lines_should_detect_a_missing_final_newline() {
+        // Given
+        let file_name = String::from("test_file_no_final_newline.txt");
+        fs::write(&file_name, "first\n\nlast").unwrap();
+
+        // When
+        let (result, file_format) = init_lines(Some(&file_name), Encoding::Utf8).unwrap();
+
+        // Then
+        assert_eq!(result, vec!["first", "", "last"]);
+        assert_eq!(file_format.line_ending, LineEnding::LF);
+        assert!(!file_format.has_final_newline);
+
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    fn save_to_file_should_preserve_the_file_format() {
+        // Given
+        let file_name = String::from("test_file_round_trip.txt");
+        let content = "first\r\n\r\nlast\r\n";
+        fs::write(&file_name, content).unwrap();
+        let (lines, file_format) = init_lines(Some(&file_name), Encoding::Utf8).unwrap();
+
+        // When
+        save_to_file(&file_name, lines.join("\n"), &file_format, false).unwrap();
+
+        // Then
+        assert_eq!(fs::read_to_string(&file_name).unwrap(), content);
+
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    fn save_to_file_should_preserve_a_single_newline_or_an_empty_file() {
+        for content in &["\n", ""] {
+            // Given
+            let file_name = String::from("test_file_single_newline.txt");
+            fs::write(&file_name, content).unwrap();
+            let (lines, file_format) = init_lines(Some(&file_name), Encoding::Utf8).unwrap();
+
+            // When
+            save_to_file(&file_name, lines.join("\n"), &file_format, false).unwrap();
+
+            // Then
+            assert_eq!(fs::read_to_string(&file_name).unwrap(), *content);
+
+            fs::remove_file(&file_name).unwrap();
+        }
+    }
+
+    #[test]
+    fn save_to_file_should_preserve_a_latin1_file() {
+        // Given
+        let file_name = String::from("test_file_latin1.txt");
+        let content = [b'c', b'a', b'f', 0xe9, b'\n'];
+        fs::write(&file_name, content).unwrap();
+        let (lines, file_format) = init_lines(Some(&file_name), Encoding::Latin1).unwrap();
+        assert_eq!(lines, vec![String::from("café")]);
+
+        // When
+        save_to_file(&file_name, lines.join("\n"), &file_format, false).unwrap();
+        let result = save_to_file(&file_name, String::from("€"), &file_format, false);
+
+        // Then
+        assert!(matches!(result, Err(RustorError::Save { .. })));
+        assert_eq!(fs::read(&file_name).unwrap(), content);
+
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    fn read_lines_should_read_the_whole_input() {
+        // Given
+        let input = "first\nsecond\n".as_bytes();
+
+        // When
+        let (lines, file_format) = read_lines(input, Encoding::Utf8).unwrap();
+
+        // Then
+        assert_eq!(lines, vec![String::from("first"), String::from("second")]);
+        assert!(file_format.has_final_newline);
+    }
+
+    #[test]
+    fn encoding_should_be_parsed_from_its_name() {
+        assert_eq!(Encoding::parse("UTF-8"), Some(Encoding::Utf8));
+        assert_eq!(Encoding::parse("latin1"), Some(Encoding::Latin1));
+        assert_eq!(Encoding::parse("ebcdic"), None);
+    }
+
+    #[test]
+    fn format_text_should_convert_line_endings() {
+        // Given
+        let file_format = FileFormat {
+            line_ending: LineEnding::CRLF,
+            has_final_newline: false,
+            encoding: Encoding::Utf8,
+        };
+
+        // When
+        let result = format_text("a\nb", &file_format);
+
+        // Then
+        assert_eq!(result, "a\r\nb");
+    }
+
+    #[test]
+    fn save_to_file_should_keep_a_backup_of_the_previous_version() {
+        // Given
+        let file_name = String::from("test_file_backup.txt");
+        let backup_name = String::from("test_file_backup.txt~");
+        fs::write(&file_name, "old\n").unwrap();
+
+        // When
+        save_to_file(&file_name, String::from("new"), &FileFormat::new(), true).unwrap();
+
+        // Then
+        assert_eq!(fs::read_to_string(&file_name).unwrap(), "new\n");
+        assert_eq!(fs::read_to_string(&backup_name).unwrap(), "old\n");
+
+        fs::remove_file(&file_name).unwrap();
+        fs::remove_file(&backup_name).unwrap();
+    }
+
+    #[test]
+    fn save_to_file_should_refuse_to_replace_a_read_only_file() {
+        // Given
+        let file_name = String::from("test_file_read_only.txt");
+        fs::write(&file_name, "old\n").unwrap();
+        let mut permissions = fs::metadata(&file_name).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&file_name, permissions).unwrap();
+
+        // When
+        let result = save_to_file(&file_name, String::from("new"), &FileFormat::new(), false);
+
+        // Then
+        assert!(matches!(result, Err(RustorError::Save { .. })));
+        assert_eq!(fs::read_to_string(&file_name).unwrap(), "old\n");
+
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn save_to_file_should_keep_the_hard_links_of_a_file() {
+        // Given
+        let file_name = String::from("test_file_hard_link.txt");
+        let link_name = String::from("test_file_hard_link_other_name.txt");
+        fs::write(&file_name, "old\n").unwrap();
+        fs::hard_link(&file_name, &link_name).unwrap();
+
+        // When
+        save_to_file(&file_name, String::from("new"), &FileFormat::new(), false).unwrap();
+
+        // Then
+        assert_eq!(fs::read_to_string(&link_name).unwrap(), "new\n");
+
+        fs::remove_file(&file_name).unwrap();
+        fs::remove_file(&link_name).unwrap();
+    }
+
+    #[test]
+    fn save_to_file_should_create_a_new_file_without_leaving_a_temporary_file() {
+        // Given
+        let file_name = String::from("test_file_new.txt");
+
+        // When
+        save_to_file(&file_name, String::from("new"), &FileFormat::new(), true).unwrap();
+
+        // Then
+        assert_eq!(fs::read_to_string(&file_name).unwrap(), "new\n");
+        assert!(!Path::new("test_file_new.txt~").exists());
+        assert!(!get_temporary_path(Path::new(&file_name)).exists());
+
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn save_to_file_should_preserve_the_permissions() {
+        // Given
+        use std::os::unix::fs::PermissionsExt;
+        let file_name = String::from("test_file_permissions.txt");
+        fs::write(&file_name, "old").unwrap();
+        fs::set_permissions(&file_name, fs::Permissions::from_mode(0o640)).unwrap();
+
+        // When
+        save_to_file(&file_name, String::from("new"), &FileFormat::new(), false).unwrap();
+
+        // Then
+        let mode = fs::metadata(&file_name).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    fn init_lines_should_fail_on_a_file_that_is_not_utf8() {
+        // Given
+        let file_name = String::from("test_file_not_utf8.txt");
+        fs::write(&file_name, [0xff, 0xfe, 0x00]).unwrap();
+
+        // When
+        let result = init_lines(Some(&file_name), Encoding::Utf8);
+
+        // Then
+        assert!(matches!(result, Err(RustorError::Load { .. })));
+
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    fn init_lines_should_return_an_empty_line_for_a_new_file() {
+        // Given
+        let file_name = String::from("test_file_that_does_not_exist.txt");
+
+        // When
+        let (result, _) = init_lines(Some(&file_name), Encoding::Utf8).unwrap();
+
+        // Then
+        assert_eq!(result, vec![String::new()]);
+    }
+
+    #[test]
+    fn save_to_file_should_fail_in_a_missing_directory() {
+        // Given
+        let file_name = "missing_directory/test_file.txt";
+
+        // When
+        let result = save_to_file(file_name, String::from("new"), &FileFormat::new(), false);
+
+        // Then
+        assert!(matches!(result, Err(RustorError::Save { .. })));
+    }
+
+    #[test]
+    fn write_emergency_copy_should_write_next_to_the_file() {
+        // Given
+        let file_name = String::from("test_file_emergency.txt");
+
+        // When
+        let result = write_emergency_copy(Some(&file_name), "unsaved", &FileFormat::new());
+
+        // Then
+        let path = result.unwrap();
+        assert_eq!(
+            path,
+            PathBuf::from("test_file_emergency.txt.rustor-emergency")
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "unsaved\n");
+
+        fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,377 @@
+use cursor::CursorPosition;
+use piece_table::PieceTable;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    Insert { index: u32, text: String },
+    Remove { index: u32, text: String },
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    edits: Vec<Edit>,
+    cursor_before: CursorPosition,
+    cursor_after: CursorPosition,
+}
+
+#[derive(Debug)]
+pub struct History {
+    undo_steps: Vec<Step>,
+    redo_steps: Vec<Step>,
+    is_last_step_sealed: bool,
+    saved_step: Option<usize>,
+    group_start: Option<usize>,
+}
+
+impl Edit {
+    fn apply(&self, piece_table: &mut PieceTable) {
+        match self {
+            Edit::Insert { index, text } => piece_table.insert(*index, text.clone()),
+            Edit::Remove { index, text } => piece_table.remove(*index, text.len()),
+        }
+    }
+
+    fn revert(&self, piece_table: &mut PieceTable) {
+        match self {
+            Edit::Insert { index, text } => piece_table.remove(*index, text.len()),
+            Edit::Remove { index, text } => piece_table.insert(*index, text.clone()),
+        }
+    }
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            undo_steps: vec![],
+            redo_steps: vec![],
+            is_last_step_sealed: true,
+            saved_step: Some(0),
+            group_start: None,
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        edit: Edit,
+        cursor_before: CursorPosition,
+        cursor_after: CursorPosition,
+    ) {
+        self.redo_steps.clear();
+        if matches!(self.saved_step, Some(step) if step > self.undo_steps.len()) {
+            self.saved_step = None;
+        }
+        if let Some(group_start) = self.group_start {
+            if self.undo_steps.len() > group_start {
+                if let Some(step) = self.undo_steps.last_mut() {
+                    step.edits.push(edit);
+                    step.cursor_after = cursor_after;
+                    return;
+                }
+            }
+        } else if !self.is_last_step_sealed {
+            if let Some(step) = self.undo_steps.last_mut() {
+                if History::should_merge(step, &edit) {
+                    if let (Some(Edit::Insert { text, .. }), Edit::Insert { text: added, .. }) =
+                        (step.edits.last_mut(), &edit)
+                    {
+                        text.push_str(added);
+                    }
+                    step.cursor_after = cursor_after;
+                    return;
+                }
+            }
+        }
+        self.is_last_step_sealed = !History::is_mergeable(&edit);
+        self.undo_steps.push(Step {
+            edits: vec![edit],
+            cursor_before,
+            cursor_after,
+        });
+    }
+
+    pub fn seal(&mut self) {
+        self.is_last_step_sealed = true;
+    }
+
+    pub fn start_group(&mut self) {
+        self.seal();
+        self.group_start = Some(self.undo_steps.len());
+    }
+
+    pub fn end_group(&mut self) {
+        self.group_start = None;
+        self.seal();
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.seal();
+        self.saved_step = Some(self.undo_steps.len());
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.saved_step != Some(self.undo_steps.len())
+    }
+
+    pub fn undo(&mut self, piece_table: &mut PieceTable) -> Option<CursorPosition> {
+        self.seal();
+        let step = self.undo_steps.pop()?;
+        for edit in step.edits.iter().rev() {
+            edit.revert(piece_table);
+        }
+        let cursor = step.cursor_before.clone();
+        self.redo_steps.push(step);
+        Some(cursor)
+    }
+
+    pub fn redo(&mut self, piece_table: &mut PieceTable) -> Option<CursorPosition> {
+        self.seal();
+        let step = self.redo_steps.pop()?;
+        for edit in &step.edits {
+            edit.apply(piece_table);
+        }
+        let cursor = step.cursor_after.clone();
+        self.undo_steps.push(step);
+        Some(cursor)
+    }
+
+    fn is_mergeable(edit: &Edit) -> bool {
+        match edit {
+            Edit::Insert { text, .. } => text.chars().count() == 1,
+            Edit::Remove { .. } => false,
+        }
+    }
+
+    fn should_merge(step: &Step, edit: &Edit) -> bool {
+        match (step.edits.as_slice(), edit) {
+            (
+                [Edit::Insert {
+                    index: previous_index,
+                    text: previous_text,
+                }],
+                Edit::Insert { index, .. },
+            ) => {
+                History::is_mergeable(edit)
+                    && *previous_index as usize + previous_text.len() == *index as usize
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor_at(x: u16) -> CursorPosition {
+        CursorPosition {
+            x,
+            y: 1,
+            y_offset: 0,
+            x_offset: 0,
+            preferred_column: None,
+        }
+    }
+
+    fn insert(piece_table: &mut PieceTable, history: &mut History, index: u32, text: &str) {
+        piece_table.insert(index, String::from(text));
+        history.record(
+            Edit::Insert {
+                index,
+                text: String::from(text),
+            },
+            cursor_at(index as u16 + 1),
+            cursor_at((index as usize + text.len()) as u16 + 1),
+        );
+    }
+
+    #[test]
+    fn undo_should_revert_an_insertion() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("This is text"));
+        let mut history = History::new();
+        insert(&mut piece_table, &mut history, 8, "a ");
+
+        // When
+        let cursor = history.undo(&mut piece_table);
+
+        // Then
+        assert_eq!(piece_table.get_text(), "This is text");
+        assert_eq!(cursor.unwrap().x, 9);
+    }
+
+    #[test]
+    fn undo_should_revert_a_removal() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("This is a text"));
+        let mut history = History::new();
+        piece_table.remove(8, 2);
+        history.record(
+            Edit::Remove {
+                index: 8,
+                text: String::from("a "),
+            },
+            cursor_at(11),
+            cursor_at(9),
+        );
+
+        // When
+        let cursor = history.undo(&mut piece_table);
+
+        // Then
+        assert_eq!(piece_table.get_text(), "This is a text");
+        assert_eq!(cursor.unwrap().x, 11);
+    }
+
+    #[test]
+    fn undo_should_group_consecutive_characters() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("This is text"));
+        let mut history = History::new();
+        insert(&mut piece_table, &mut history, 8, "a");
+        insert(&mut piece_table, &mut history, 9, " ");
+        insert(&mut piece_table, &mut history, 10, "b");
+
+        // When
+        history.undo(&mut piece_table);
+
+        // Then
+        assert_eq!(piece_table.get_text(), "This is text");
+        assert!(history.undo(&mut piece_table).is_none());
+    }
+
+    #[test]
+    fn undo_should_not_group_characters_after_a_seal() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("This is text"));
+        let mut history = History::new();
+        insert(&mut piece_table, &mut history, 8, "a");
+        history.seal();
+        insert(&mut piece_table, &mut history, 9, " ");
+
+        // When
+        history.undo(&mut piece_table);
+
+        // Then
+        assert_eq!(piece_table.get_text(), "This is atext");
+    }
+
+    #[test]
+    fn undo_should_not_group_characters_that_are_not_consecutive() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("This is text"));
+        let mut history = History::new();
+        insert(&mut piece_table, &mut history, 8, "a");
+        insert(&mut piece_table, &mut history, 0, "!");
+
+        // When
+        history.undo(&mut piece_table);
+
+        // Then
+        assert_eq!(piece_table.get_text(), "This is atext");
+    }
+
+    #[test]
+    fn redo_should_reapply_an_undone_step() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("This is text"));
+        let mut history = History::new();
+        insert(&mut piece_table, &mut history, 8, "a");
+        insert(&mut piece_table, &mut history, 9, " ");
+        history.undo(&mut piece_table);
+
+        // When
+        let cursor = history.redo(&mut piece_table);
+
+        // Then
+        assert_eq!(piece_table.get_text(), "This is a text");
+        assert_eq!(cursor.unwrap().x, 11);
+    }
+
+    #[test]
+    fn record_should_clear_the_redo_steps() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("This is text"));
+        let mut history = History::new();
+        insert(&mut piece_table, &mut history, 8, "a");
+        history.undo(&mut piece_table);
+
+        // When
+        insert(&mut piece_table, &mut history, 0, "!");
+
+        // Then
+        assert!(history.redo(&mut piece_table).is_none());
+        assert_eq!(piece_table.get_text(), "!This is text");
+    }
+
+    #[test]
+    fn is_modified_should_be_false_after_undoing_back_to_the_saved_state() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("This is text"));
+        let mut history = History::new();
+        insert(&mut piece_table, &mut history, 8, "a");
+        history.mark_saved();
+        insert(&mut piece_table, &mut history, 9, " ");
+        assert!(history.is_modified());
+
+        // When
+        history.undo(&mut piece_table);
+
+        // Then
+        assert!(!history.is_modified());
+    }
+
+    #[test]
+    fn is_modified_should_stay_true_when_the_saved_state_is_discarded() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("This is text"));
+        let mut history = History::new();
+        insert(&mut piece_table, &mut history, 8, "a");
+        history.mark_saved();
+        history.undo(&mut piece_table);
+
+        // When
+        insert(&mut piece_table, &mut history, 0, "!");
+
+        // Then
+        assert!(history.is_modified());
+        history.undo(&mut piece_table);
+        assert!(history.is_modified());
+    }
+
+    #[test]
+    fn undo_should_revert_a_group_of_edits_as_one_step() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("cat and cat"));
+        let mut history = History::new();
+        history.start_group();
+        piece_table.remove(8, 3);
+        history.record(
+            Edit::Remove {
+                index: 8,
+                text: String::from("cat"),
+            },
+            cursor_at(1),
+            cursor_at(1),
+        );
+        insert(&mut piece_table, &mut history, 8, "dog");
+        piece_table.remove(0, 3);
+        history.record(
+            Edit::Remove {
+                index: 0,
+                text: String::from("cat"),
+            },
+            cursor_at(1),
+            cursor_at(1),
+        );
+        insert(&mut piece_table, &mut history, 0, "dog");
+        history.end_group();
+        assert_eq!(piece_table.get_text(), "dog and dog");
+
+        // When
+        history.undo(&mut piece_table);
+
+        // Then
+        assert_eq!(piece_table.get_text(), "cat and cat");
+        assert!(history.undo(&mut piece_table).is_none());
+    }
+}
@@ -1,89 +1,206 @@
+extern crate libc;
+extern crate regex;
+extern crate signal_hook;
 extern crate termion;
+extern crate unicode_segmentation;
+extern crate unicode_width;
 
 use std::env;
 use std::io::{stdin, stdout, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
 
-use termion::input::TermRead;
+use termion::event::Key;
 use termion::raw::IntoRawMode;
 use termion::screen::*;
 
+use arguments::*;
+use config::Config;
 use editor::*;
+use error::RustorError;
+use event::*;
 use file::*;
+use terminal::*;
 use window::*;
 
+mod arguments;
+mod clipboard;
+mod column;
+mod config;
 mod cursor;
 mod editor;
+mod error;
+mod event;
 mod file;
+mod history;
+mod minibuffer;
 mod piece_table;
+mod replace;
+mod terminal;
 mod window;
+mod word;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    check_arguments(&args);
-    let file_name_option = get_file_name(&args);
-
-    let stdin = stdin();
-    let mut stdout = AlternateScreen::from(stdout().into_raw_mode().unwrap());
-
-    print_first_line(&mut stdout);
+    let mut arguments = match parse_arguments(&args) {
+        Ok(Command::Edit(arguments)) => arguments,
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Ok(Command::Version) => {
+            println!("rustor {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Err(error) => {
+            eprintln!("rustor: {}", error);
+            eprintln!("Try 'rustor --help' for more information.");
+            process::exit(2);
+        }
+    };
 
-    let lines = init_lines(file_name_option);
-    let mut editor = Editor::from(lines);
+    arguments.add_piped_stdin(!termion::is_tty(&stdin()));
 
-    print_text(&mut stdout, &editor);
-    stdout.flush().unwrap();
+    if let Err(error) = run(&arguments) {
+        eprintln!("rustor: {}", error);
+        process::exit(1);
+    }
+}
 
-    for c in stdin.keys() {
-        let (_, terminal_height) = termion::terminal_size().unwrap();
-        let should_continue = handle_key_press(c, &mut editor, file_name_option, terminal_height);
-        if !should_continue {
-            break;
+fn open_editor(
+    file: Option<&FileArgument>,
+    arguments: &Arguments,
+    config: &Config,
+    editor_height: u16,
+) -> Result<Editor, RustorError> {
+    let file_name_option = file
+        .filter(|file| !file.is_stdin())
+        .map(|file| &file.file_name);
+    let result = match file {
+        Some(file) if file.is_stdin() => Ok(read_lines(stdin(), arguments.encoding)?),
+        _ => init_lines(file_name_option, arguments.encoding),
+    };
+    let mut editor = match result {
+        Ok((lines, file_format)) => Editor::from(lines).with_file_format(file_format),
+        Err(error) => {
+            let mut editor = Editor::from(vec![String::new()]);
+            editor.can_overwrite_file = false;
+            editor.message = Some(error.to_string());
+            editor
         }
-        print_text(&mut stdout, &editor);
-        stdout.flush().unwrap();
     }
-
-    write!(stdout, "{}", termion::cursor::Show).unwrap();
+    .with_file_name(file_name_option.cloned())
+    .with_config(config.clone())
+    .with_readonly(arguments.is_readonly);
+    if let Some(position) = file.and_then(|file| file.position) {
+        editor.go_to_position(position, editor_height);
+    }
+    Ok(editor)
 }
 
-fn check_arguments(args: &[String]) {
-    if args.len() > 2 {
-        panic!("Too many arguments")
+fn open_editors(arguments: &Arguments) -> Result<Vec<Editor>, RustorError> {
+    let config = match &arguments.config_path {
+        Some(config_path) => Config::load_from(config_path)?,
+        None => Config::load(),
+    };
+    let (_, terminal_height) = termion::terminal_size()?;
+    let editor_height = get_editor_height(terminal_height);
+    if arguments.files.is_empty() {
+        return Ok(vec![open_editor(None, arguments, &config, editor_height)?]);
     }
+    arguments
+        .files
+        .iter()
+        .map(|file| open_editor(Some(file), arguments, &config, editor_height))
+        .collect()
 }
 
-fn get_file_name(args: &[String]) -> Option<&String> {
-    if args.len() == 1 {
-        return None;
+fn run(arguments: &Arguments) -> Result<(), RustorError> {
+    let mut editors = open_editors(arguments)?;
+
+    save_terminal_mode();
+    install_panic_hook();
+    let mut stdout = stdout().into_raw_mode()?.into_alternate_screen()?;
+    let result = match panic::catch_unwind(AssertUnwindSafe(|| edit(&mut stdout, &mut editors))) {
+        Ok(result) => result,
+        Err(_) => Err(RustorError::Panic),
+    };
+    let emergency_copies: Vec<_> = if result.is_err() {
+        editors
+            .iter()
+            .filter(|editor| editor.is_modified())
+            .map(|editor| {
+                write_emergency_copy(
+                    editor.file_name.as_ref(),
+                    &editor.get_text(),
+                    &editor.file_format,
+                )
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+    drop(stdout);
+    restore_terminal();
+
+    for emergency_copy in emergency_copies {
+        match emergency_copy {
+            Ok(path) => eprintln!("rustor: unsaved changes written to {}", path.display()),
+            Err(error) => eprintln!("rustor: {}", error),
+        }
     }
-    Some(&args[1])
+    result
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn get_file_name_should_return_nothing_if_no_args() {
-        // Given
-        let args = vec![String::from("rustor")];
-
-        // When
-        let result = get_file_name(&args);
+fn show_file_number(editors: &mut [Editor], current: usize) {
+    let number_of_files = editors.len();
+    editors[current].message = match number_of_files {
+        1 => None,
+        _ => Some(format!("File {} of {}", current + 1, number_of_files)),
+    };
+}
 
-        // Then
-        assert_eq!(None, result);
+fn edit<W: Write>(stdout: &mut W, editors: &mut Vec<Editor>) -> Result<(), RustorError> {
+    let events = listen_events()?;
+    let mut current = 0;
+    if editors[current].message.is_none() {
+        show_file_number(editors, current);
     }
-
-    #[test]
-    fn get_file_name_should_return_the_name_of_the_file() {
-        // Given
-        let args = vec![String::from("rustor"), String::from("stuff.txt")];
-
-        // When
-        let result = get_file_name(&args);
-
-        // Then
-        assert_eq!(Some(&String::from("stuff.txt")), result);
+    write!(stdout, "{}", ENABLE_BRACKETED_PASTE)?;
+    print_first_line(stdout)?;
+    print_text(stdout, &mut editors[current])?;
+    stdout.flush()?;
+
+    for event in events.iter() {
+        let (_, terminal_height) = termion::terminal_size()?;
+        let editor_height = get_editor_height(terminal_height);
+        let is_editing = editors[current].mode == Mode::Edit;
+        match event {
+            Event::Key(Ok(Key::AltRight)) if is_editing => {
+                current = (current + 1) % editors.len();
+                show_file_number(editors, current);
+            }
+            Event::Key(Ok(Key::AltLeft)) if is_editing => {
+                current = (current + editors.len() - 1) % editors.len();
+                show_file_number(editors, current);
+            }
+            Event::Key(key) => {
+                if !handle_key_press(key, &mut editors[current], editor_height) {
+                    editors.remove(current);
+                    if editors.is_empty() {
+                        break;
+                    }
+                    current %= editors.len();
+                    show_file_number(editors, current);
+                }
+            }
+            Event::Paste(text) => {
+                handle_paste(&text, &mut editors[current], editor_height);
+            }
+            Event::Signal(signal) => return Err(RustorError::Signal(signal)),
+        }
+        print_text(stdout, &mut editors[current])?;
+        stdout.flush()?;
     }
+    Ok(())
 }
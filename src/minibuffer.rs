@@ -0,0 +1,124 @@
+use termion::event::Key;
+
+use cursor::{get_byte_index_of_grapheme, get_number_of_graphemes};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minibuffer {
+    pub label: String,
+    pub input: String,
+    pub x: u16,
+}
+
+impl Minibuffer {
+    pub fn new(label: &str) -> Minibuffer {
+        Minibuffer {
+            label: String::from(label),
+            input: String::new(),
+            x: 1,
+        }
+    }
+
+    pub fn with_input(mut self, input: &str) -> Minibuffer {
+        self.input = String::from(input);
+        self.x = get_number_of_graphemes(input) + 1;
+        self
+    }
+
+    fn get_byte_index(&self) -> usize {
+        get_byte_index_of_grapheme(&self.input, self.x as usize - 1)
+    }
+
+    pub fn insert(&mut self, c: char) {
+        let index = self.get_byte_index();
+        self.input.insert(index, c);
+        let inserted_end = index + c.len_utf8();
+        self.x = get_number_of_graphemes(&self.input[..inserted_end]) + 1;
+    }
+
+    pub fn remove(&mut self) {
+        if self.x == 1 {
+            return;
+        }
+        let end = self.get_byte_index();
+        let start = get_byte_index_of_grapheme(&self.input, self.x as usize - 2);
+        self.input.replace_range(start..end, "");
+        self.x -= 1;
+    }
+
+    pub fn move_left(&mut self) {
+        if self.x > 1 {
+            self.x -= 1;
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.x <= get_number_of_graphemes(&self.input) {
+            self.x += 1;
+        }
+    }
+
+    pub fn handle_key_press(&mut self, key: Key) {
+        match key {
+            Key::Char('\t') | Key::Char('\n') => (),
+            Key::Char(c) => self.insert(c),
+            Key::Backspace => self.remove(),
+            Key::Left => self.move_left(),
+            Key::Right => self.move_right(),
+            Key::Home => self.x = 1,
+            Key::End => self.x = get_number_of_graphemes(&self.input) + 1,
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_should_add_characters_at_the_cursor() {
+        // Given
+        let mut minibuffer = Minibuffer::new("Save as: ").with_input("notes.txt");
+        minibuffer.move_left();
+        minibuffer.move_left();
+        minibuffer.move_left();
+
+        // When
+        minibuffer.insert('m');
+        minibuffer.insert('d');
+
+        // Then
+        assert_eq!(minibuffer.input, "notes.mdtxt");
+        assert_eq!(minibuffer.x, 9);
+    }
+
+    #[test]
+    fn remove_should_delete_the_grapheme_before_the_cursor() {
+        // Given
+        let mut minibuffer = Minibuffer::new("Save as: ").with_input("café.txt");
+        minibuffer.x = 5;
+
+        // When
+        minibuffer.remove();
+
+        // Then
+        assert_eq!(minibuffer.input, "caf.txt");
+        assert_eq!(minibuffer.x, 4);
+    }
+
+    #[test]
+    fn handle_key_press_should_keep_the_cursor_inside_the_input() {
+        // Given
+        let mut minibuffer = Minibuffer::new("Save as: ").with_input("ab");
+
+        // When
+        minibuffer.handle_key_press(Key::Right);
+        minibuffer.handle_key_press(Key::Home);
+        minibuffer.handle_key_press(Key::Left);
+
+        // Then
+        assert_eq!(minibuffer.x, 1);
+        minibuffer.handle_key_press(Key::Backspace);
+        assert_eq!(minibuffer.input, "ab");
+    }
+}
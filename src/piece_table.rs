@@ -1,10 +1,16 @@
+use std::cmp;
+
 use piece_table::NodeType::{ADDED, ORIGINAL};
 
+type Tree = Option<Box<TreeNode>>;
+
 #[derive(Debug, Clone)]
 pub struct PieceTable {
     original: String,
     added: String,
-    nodes: Vec<Node>,
+    original_line_breaks: Vec<usize>,
+    added_line_breaks: Vec<usize>,
+    root: Tree,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -12,14 +18,29 @@ pub struct Node {
     node_type: NodeType,
     start: u32,
     length: usize,
+    line_breaks: usize,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(PartialEq, Debug, Copy, Clone)]
 enum NodeType {
     ORIGINAL,
     ADDED,
 }
 
+/// AVL tree of pieces, ordered by their position in the text. Each tree node
+/// keeps the length and the number of line breaks of its whole subtree so
+/// offsets and lines can be found by walking down a single branch.
+#[derive(Debug, Clone)]
+struct TreeNode {
+    piece: Node,
+    left: Tree,
+    right: Tree,
+    height: usize,
+    length: usize,
+    line_breaks: usize,
+}
+
 impl PartialEq<Node> for Node {
     fn eq(&self, other: &Node) -> bool {
         self.node_type == other.node_type
@@ -30,89 +51,540 @@ impl PartialEq<Node> for Node {
 
 impl PartialEq<PieceTable> for PieceTable {
     fn eq(&self, other: &PieceTable) -> bool {
-        self.original == other.original && self.added == other.added && self.nodes == other.nodes
+        self.original == other.original
+            && self.added == other.added
+            && self.get_nodes() == other.get_nodes()
+    }
+}
+
+fn find_line_breaks(text: &str, offset: usize) -> Vec<usize> {
+    text.match_indices('\n')
+        .map(|(index, _)| offset + index)
+        .collect()
+}
+
+/*
+ *
+ * TREE
+ *
+ */
+
+fn height(tree: &Tree) -> usize {
+    tree.as_ref().map_or(0, |node| node.height)
+}
+
+fn length(tree: &Tree) -> usize {
+    tree.as_ref().map_or(0, |node| node.length)
+}
+
+fn line_breaks(tree: &Tree) -> usize {
+    tree.as_ref().map_or(0, |node| node.line_breaks)
+}
+
+/// In-order walk over the pieces, yielding each piece with its index in the
+/// text. The stack holds the nodes still to visit, the next one on top.
+struct PieceWalk<'a> {
+    stack: Vec<(&'a TreeNode, usize)>,
+    is_forward: bool,
+}
+
+impl<'a> PieceWalk<'a> {
+    /// Starts at the piece containing `start_index`.
+    fn forward(tree: &'a Tree, start_index: usize) -> PieceWalk<'a> {
+        let mut stack = vec![];
+        let mut tree = tree;
+        let mut offset = 0;
+        while let Some(node) = tree {
+            let node_start_index = offset + length(&node.left);
+            let node_stop_index = node_start_index + node.piece.length;
+            if start_index < node_start_index {
+                stack.push((node.as_ref(), node_start_index));
+                tree = &node.left;
+            } else if start_index < node_stop_index {
+                stack.push((node.as_ref(), node_start_index));
+                break;
+            } else {
+                tree = &node.right;
+                offset = node_stop_index;
+            }
+        }
+        PieceWalk {
+            stack,
+            is_forward: true,
+        }
+    }
+
+    /// Starts at the piece containing the byte before `stop_index`.
+    fn backward(tree: &'a Tree, stop_index: usize) -> PieceWalk<'a> {
+        let mut stack = vec![];
+        let mut tree = tree;
+        let mut offset = 0;
+        while let Some(node) = tree {
+            let node_start_index = offset + length(&node.left);
+            let node_stop_index = node_start_index + node.piece.length;
+            if stop_index <= node_start_index {
+                tree = &node.left;
+            } else if stop_index <= node_stop_index {
+                stack.push((node.as_ref(), node_start_index));
+                break;
+            } else {
+                stack.push((node.as_ref(), node_start_index));
+                tree = &node.right;
+                offset = node_stop_index;
+            }
+        }
+        PieceWalk {
+            stack,
+            is_forward: false,
+        }
+    }
+}
+
+impl<'a> Iterator for PieceWalk<'a> {
+    type Item = (usize, Node);
+
+    fn next(&mut self) -> Option<(usize, Node)> {
+        let (node, node_start_index) = self.stack.pop()?;
+        if self.is_forward {
+            let mut tree = &node.right;
+            let offset = node_start_index + node.piece.length;
+            while let Some(child) = tree {
+                self.stack
+                    .push((child.as_ref(), offset + length(&child.left)));
+                tree = &child.left;
+            }
+        } else {
+            let mut tree = &node.left;
+            let mut offset = node_start_index - length(&node.left);
+            while let Some(child) = tree {
+                let child_start_index = offset + length(&child.left);
+                self.stack.push((child.as_ref(), child_start_index));
+                offset = child_start_index + child.piece.length;
+                tree = &child.right;
+            }
+        }
+        Some((node_start_index, node.piece))
+    }
+}
+
+fn build_tree_node(left: Tree, piece: Node, right: Tree) -> Box<TreeNode> {
+    Box::new(TreeNode {
+        height: cmp::max(height(&left), height(&right)) + 1,
+        length: length(&left) + piece.length + length(&right),
+        line_breaks: line_breaks(&left) + piece.line_breaks + line_breaks(&right),
+        piece,
+        left,
+        right,
+    })
+}
+
+fn rotate_left(node: TreeNode) -> Box<TreeNode> {
+    let TreeNode {
+        piece, left, right, ..
+    } = node;
+    let right = right.expect("a left rotation needs a right child");
+    build_tree_node(
+        Some(build_tree_node(left, piece, right.left)),
+        right.piece,
+        right.right,
+    )
+}
+
+fn rotate_right(node: TreeNode) -> Box<TreeNode> {
+    let TreeNode {
+        piece, left, right, ..
+    } = node;
+    let left = left.expect("a right rotation needs a left child");
+    build_tree_node(
+        left.left,
+        left.piece,
+        Some(build_tree_node(left.right, piece, right)),
+    )
+}
+
+fn rebalance(node: Box<TreeNode>) -> Box<TreeNode> {
+    let left_height = height(&node.left);
+    let right_height = height(&node.right);
+    if left_height > right_height + 1 {
+        let TreeNode {
+            piece, left, right, ..
+        } = *node;
+        let mut left = left.expect("a left heavy node has a left child");
+        if height(&left.right) > height(&left.left) {
+            left = rotate_left(*left);
+        }
+        rotate_right(*build_tree_node(Some(left), piece, right))
+    } else if right_height > left_height + 1 {
+        let TreeNode {
+            piece, left, right, ..
+        } = *node;
+        let mut right = right.expect("a right heavy node has a right child");
+        if height(&right.left) > height(&right.right) {
+            right = rotate_right(*right);
+        }
+        rotate_left(*build_tree_node(left, piece, Some(right)))
+    } else {
+        node
+    }
+}
+
+/// Builds a balanced tree holding `left`, then `piece`, then `right`,
+/// whatever the difference of height between `left` and `right`.
+fn join(left: Tree, piece: Node, right: Tree) -> Box<TreeNode> {
+    let left_height = height(&left);
+    let right_height = height(&right);
+    if left_height > right_height + 1 {
+        let left = left.expect("a higher tree is not empty");
+        rebalance(build_tree_node(
+            left.left,
+            left.piece,
+            Some(join(left.right, piece, right)),
+        ))
+    } else if right_height > left_height + 1 {
+        let right = right.expect("a higher tree is not empty");
+        rebalance(build_tree_node(
+            Some(join(left, piece, right.left)),
+            right.piece,
+            right.right,
+        ))
+    } else {
+        build_tree_node(left, piece, right)
+    }
+}
+
+fn split_last(node: TreeNode) -> (Tree, Node) {
+    let TreeNode {
+        piece, left, right, ..
+    } = node;
+    match right {
+        Some(right) => {
+            let (rest, last) = split_last(*right);
+            (Some(join(left, piece, rest)), last)
+        }
+        None => (left, piece),
+    }
+}
+
+fn concat(left: Tree, right: Tree) -> Tree {
+    match left {
+        Some(left) => {
+            let (rest, last) = split_last(*left);
+            Some(join(rest, last, right))
+        }
+        None => right,
+    }
+}
+
+fn get_last_piece(tree: &Tree) -> Option<&Node> {
+    let mut last_piece = None;
+    let mut current = tree;
+    while let Some(node) = current {
+        last_piece = Some(&node.piece);
+        current = &node.right;
+    }
+    last_piece
+}
+
+fn collect_pieces(tree: &Tree, pieces: &mut Vec<Node>) {
+    if let Some(node) = tree {
+        collect_pieces(&node.left, pieces);
+        pieces.push(node.piece);
+        collect_pieces(&node.right, pieces);
     }
 }
 
 impl PieceTable {
     pub fn new(original: String) -> PieceTable {
         let original_length = original.len();
+        let original_line_breaks = find_line_breaks(&original, 0);
         let original_node = Node {
             node_type: ORIGINAL,
             start: 0,
             length: original_length,
+            line_breaks: original_line_breaks.len(),
+        };
+        let root = if original_length == 0 {
+            None
+        } else {
+            Some(build_tree_node(None, original_node, None))
         };
         PieceTable {
             original,
             added: String::new(),
-            nodes: vec![original_node],
+            original_line_breaks,
+            added_line_breaks: vec![],
+            root,
+        }
+    }
+
+    fn build_node(&self, node_type: NodeType, start: u32, length: usize) -> Node {
+        let line_breaks = self.get_buffer_line_breaks(node_type);
+        let start_index = start as usize;
+        let stop_index = start_index + length;
+        Node {
+            node_type,
+            start,
+            length,
+            line_breaks: line_breaks.partition_point(|&i| i < stop_index)
+                - line_breaks.partition_point(|&i| i < start_index),
+        }
+    }
+
+    fn get_buffer(&self, node_type: NodeType) -> &str {
+        match node_type {
+            ADDED => &self.added,
+            ORIGINAL => &self.original,
         }
     }
 
+    fn get_buffer_line_breaks(&self, node_type: NodeType) -> &[usize] {
+        match node_type {
+            ADDED => &self.added_line_breaks,
+            ORIGINAL => &self.original_line_breaks,
+        }
+    }
+
+    fn get_nth_line_break_of_node(&self, node: &Node, nth: usize) -> usize {
+        let line_breaks = self.get_buffer_line_breaks(node.node_type);
+        let first_line_break = line_breaks.partition_point(|&i| i < node.start as usize);
+        line_breaks[first_line_break + nth] - node.start as usize
+    }
+
+    fn push_added(&mut self, text: &str) -> usize {
+        let add_start_index = self.added.len();
+        self.added_line_breaks
+            .extend(find_line_breaks(text, add_start_index));
+        self.added.push_str(text);
+        add_start_index
+    }
+
+    fn get_nodes(&self) -> Vec<Node> {
+        let mut nodes = vec![];
+        collect_pieces(&self.root, &mut nodes);
+        nodes
+    }
+
     pub fn get_text(&self) -> String {
-        let mut text = String::from("");
-        for node in &self.nodes {
-            let start = node.start as usize;
-            let stop = start + node.length;
-
-            match node.node_type {
-                ADDED => text.push_str(&self.added[start..stop]),
-                ORIGINAL => text.push_str(&self.original[start..stop]),
+        self.get_text_range(0, self.get_length())
+    }
+
+    pub fn get_length(&self) -> usize {
+        length(&self.root)
+    }
+
+    pub fn get_text_range(&self, start_index: u32, length: usize) -> String {
+        let start_index = start_index as usize;
+        let mut text = String::new();
+        self.collect_text(&self.root, start_index, start_index + length, &mut text);
+        text
+    }
+
+    fn collect_text(&self, tree: &Tree, start_index: usize, stop_index: usize, text: &mut String) {
+        let node = match tree {
+            Some(node) => node,
+            None => return,
+        };
+        let node_start_index = length(&node.left);
+        let node_stop_index = node_start_index + node.piece.length;
+        if start_index < node_start_index {
+            self.collect_text(&node.left, start_index, stop_index, text);
+        }
+        if start_index < node_stop_index && stop_index > node_start_index {
+            let start = node.piece.start as usize + cmp::max(start_index, node_start_index)
+                - node_start_index;
+            let stop = node.piece.start as usize + cmp::min(stop_index, node_stop_index)
+                - node_start_index;
+            text.push_str(&self.get_buffer(node.piece.node_type)[start..stop]);
+        }
+        if stop_index > node_stop_index {
+            self.collect_text(
+                &node.right,
+                start_index.saturating_sub(node_stop_index),
+                stop_index - node_stop_index,
+                text,
+            );
+        }
+    }
+
+    pub fn get_line_start_index(&self, line_index: usize) -> u32 {
+        if line_index == 0 {
+            return 0;
+        }
+        if line_index > line_breaks(&self.root) {
+            return self.get_length() as u32;
+        }
+        let mut line_index = line_index;
+        let mut text_index = 0;
+        let mut current = &self.root;
+        while let Some(node) = current {
+            let line_breaks_before_node = line_breaks(&node.left);
+            if line_index <= line_breaks_before_node {
+                current = &node.left;
+            } else if line_index <= line_breaks_before_node + node.piece.line_breaks {
+                let nth = line_index - line_breaks_before_node - 1;
+                let line_break_index = self.get_nth_line_break_of_node(&node.piece, nth);
+                return (text_index + length(&node.left) + line_break_index + 1) as u32;
+            } else {
+                line_index -= line_breaks_before_node + node.piece.line_breaks;
+                text_index += length(&node.left) + node.piece.length;
+                current = &node.right;
             }
         }
+        text_index as u32
+    }
 
-        text
+    pub fn get_line_index(&self, index: u32) -> usize {
+        let mut index = index as usize;
+        let mut line_index = 0;
+        let mut current = &self.root;
+        while let Some(node) = current {
+            let node_start_index = length(&node.left);
+            let node_stop_index = node_start_index + node.piece.length;
+            if index < node_start_index {
+                current = &node.left;
+            } else if index < node_stop_index {
+                let buffer_line_breaks = self.get_buffer_line_breaks(node.piece.node_type);
+                let piece_start = node.piece.start as usize;
+                let piece_index = piece_start + index - node_start_index;
+                let line_breaks_in_piece = buffer_line_breaks.partition_point(|&i| i < piece_index)
+                    - buffer_line_breaks.partition_point(|&i| i < piece_start);
+                return line_index + line_breaks(&node.left) + line_breaks_in_piece;
+            } else {
+                line_index += line_breaks(&node.left) + node.piece.line_breaks;
+                index -= node_stop_index;
+                current = &node.right;
+            }
+        }
+        line_index
+    }
+
+    fn get_piece_text(&self, piece: &Node) -> &str {
+        let start = piece.start as usize;
+        &self.get_buffer(piece.node_type)[start..start + piece.length]
+    }
+
+    /// Searches piece by piece from the start index, keeping the last
+    /// `query.len() - 1` bytes to find the matches spanning two pieces.
+    pub fn find(&self, query: &str, start_index: u32) -> Option<u32> {
+        let overlap = query.len().saturating_sub(1);
+        let mut window = String::new();
+        let mut window_start = start_index as usize;
+        for (piece_start, piece) in PieceWalk::forward(&self.root, window_start) {
+            let text = self.get_piece_text(&piece);
+            let mut offset = window_start.saturating_sub(piece_start);
+            while !text.is_char_boundary(offset) {
+                offset += 1;
+            }
+            if window.is_empty() {
+                window_start = piece_start + offset;
+            }
+            window.push_str(&text[offset..]);
+            if let Some(index) = window.find(query) {
+                return Some((window_start + index) as u32);
+            }
+            let mut kept_start = window.len().saturating_sub(overlap);
+            while !window.is_char_boundary(kept_start) {
+                kept_start += 1;
+            }
+            window.drain(..kept_start);
+            window_start += kept_start;
+        }
+        None
+    }
+
+    pub fn rfind(&self, query: &str, stop_index: u32) -> Option<u32> {
+        let overlap = query.len().saturating_sub(1);
+        let stop_index = stop_index as usize;
+        let mut window = String::new();
+        for (piece_start, piece) in PieceWalk::backward(&self.root, stop_index) {
+            let text = self.get_piece_text(&piece);
+            let mut length = cmp::min(stop_index - piece_start, text.len());
+            while !text.is_char_boundary(length) {
+                length -= 1;
+            }
+            window.insert_str(0, &text[..length]);
+            if let Some(index) = window.rfind(query) {
+                return Some((piece_start + index) as u32);
+            }
+            let mut kept_length = cmp::min(overlap, window.len());
+            while !window.is_char_boundary(kept_length) {
+                kept_length += 1;
+            }
+            window.truncate(kept_length);
+        }
+        None
     }
 
     pub fn get_range_lines(&self, start: usize, stop: usize) -> Vec<String> {
-        let lines: Vec<String> = self
-            .get_text()
+        let number_of_lines = self.get_number_of_lines();
+        let stop = cmp::min(stop, number_of_lines);
+        if start >= stop {
+            return vec![];
+        }
+        let start_index = self.get_line_start_index(start);
+        let stop_index = if stop == number_of_lines {
+            self.get_length() as u32
+        } else {
+            self.get_line_start_index(stop) - 1
+        };
+
+        self.get_text_range(start_index, (stop_index - start_index) as usize)
             .split('\n')
             .map(String::from)
-            .enumerate()
-            .filter(|&(i, _)| i >= start && i < stop)
-            .map(|(_, e)| e)
-            .collect::<Vec<String>>();
+            .collect()
+    }
 
-        lines
+    pub fn get_line(&self, line_index: usize) -> Option<String> {
+        self.get_range_lines(line_index, line_index + 1).pop()
     }
 
+    #[allow(dead_code)]
     pub fn get_all_lines(&self) -> Vec<String> {
         let lines: Vec<String> = self.get_text().split('\n').map(String::from).collect();
         lines
     }
 
     pub fn get_number_of_lines(&self) -> usize {
-        self.get_text().matches('\n').count() as usize + 1
+        line_breaks(&self.root) + 1
     }
 
     #[allow(dead_code)]
     pub fn push(mut self, text: String) -> PieceTable {
-        let previous_node = self.nodes.iter().last().unwrap();
-        let should_update_previous_node = previous_node.node_type == ADDED
-            && previous_node.start as usize + previous_node.length == self.added.len();
-
-        if should_update_previous_node {
-            let new_node = Node {
-                node_type: ADDED,
-                start: previous_node.start,
-                length: previous_node.length + text.len(),
-            };
-            self.nodes.splice(
-                self.nodes.len() - 1..self.nodes.len(),
-                vec![new_node].into_iter(),
-            );
+        let length = self.get_length() as u32;
+        self.insert(length, text);
+        self
+    }
+
+    fn split(&self, tree: Tree, index: usize) -> (Tree, Tree) {
+        let node = match tree {
+            Some(node) => node,
+            None => return (None, None),
+        };
+        let TreeNode {
+            piece, left, right, ..
+        } = *node;
+        let node_start_index = length(&left);
+        let node_stop_index = node_start_index + piece.length;
+        if index <= node_start_index {
+            let (left_left, left_right) = self.split(left, index);
+            (left_left, Some(join(left_right, piece, right)))
+        } else if index >= node_stop_index {
+            let (right_left, right_right) = self.split(right, index - node_stop_index);
+            (Some(join(left, piece, right_left)), right_right)
         } else {
-            let new_node = Node {
-                node_type: ADDED,
-                start: self.added.len() as u32,
-                length: text.len(),
-            };
-            self.nodes.push(new_node);
+            let length_before_split = index - node_start_index;
+            let node_before_split =
+                self.build_node(piece.node_type, piece.start, length_before_split);
+            let node_after_split = self.build_node(
+                piece.node_type,
+                piece.start + length_before_split as u32,
+                piece.length - length_before_split,
+            );
+            (
+                Some(join(left, node_before_split, None)),
+                Some(join(None, node_after_split, right)),
+            )
         }
-        self.added.push_str(&text);
-
-        self
     }
 
     /*
@@ -122,109 +594,10 @@ impl PieceTable {
      */
 
     pub fn remove(&mut self, start_index: u32, length: usize) {
-        let remove_start_index = start_index as usize;
-        let remove_stop_index = remove_start_index + length;
-        let mut text_index = 0 as usize;
-        self.nodes = self
-            .nodes
-            .iter_mut()
-            .flat_map(|node| {
-                let node_start_index = text_index;
-                let node_stop_index = text_index + node.length;
-                let current_text_index = text_index;
-                text_index += node.length;
-                if PieceTable::is_deletion_within_the_node(
-                    node_start_index,
-                    node_stop_index,
-                    remove_start_index,
-                    remove_stop_index,
-                ) {
-                    let second_node_start = (remove_stop_index - current_text_index) as u32;
-                    return vec![
-                        Node {
-                            node_type: node.node_type,
-                            start: node.start,
-                            length: remove_start_index - node_start_index,
-                        },
-                        Node {
-                            node_type: node.node_type,
-                            start: second_node_start,
-                            length: node.length
-                                - (second_node_start as usize - node.start as usize),
-                        },
-                    ];
-                } else if PieceTable::is_node_within_deletion(
-                    node_start_index,
-                    node_stop_index,
-                    remove_start_index,
-                    remove_stop_index,
-                ) {
-                    return vec![];
-                } else if PieceTable::is_deletion_at_the_beginning_of_node(
-                    node_start_index,
-                    node_stop_index,
-                    remove_start_index,
-                    remove_stop_index,
-                ) {
-                    let start_diff = remove_stop_index - node_start_index;
-                    return vec![Node {
-                        node_type: node.node_type,
-                        start: node.start + start_diff as u32,
-                        length: node.length - start_diff,
-                    }];
-                } else if PieceTable::is_deletion_at_the_end_of_node(
-                    node_start_index,
-                    node_stop_index,
-                    remove_start_index,
-                    remove_stop_index,
-                ) {
-                    return vec![Node {
-                        node_type: node.node_type,
-                        start: node.start,
-                        length: node.length + remove_start_index - node_stop_index,
-                    }];
-                } else {
-                    vec![*node]
-                    // return vec![node.clone()];
-                }
-            })
-            .collect();
-    }
-
-    fn is_deletion_within_the_node(
-        node_start_index: usize,
-        node_stop_index: usize,
-        remove_start_index: usize,
-        remove_stop_index: usize,
-    ) -> bool {
-        node_start_index < remove_start_index && node_stop_index > remove_stop_index
-    }
-
-    fn is_node_within_deletion(
-        node_start_index: usize,
-        node_stop_index: usize,
-        remove_start_index: usize,
-        remove_stop_index: usize,
-    ) -> bool {
-        node_start_index >= remove_start_index && node_stop_index <= remove_stop_index
-    }
-
-    fn is_deletion_at_the_beginning_of_node(
-        node_start_index: usize,
-        _node_stop_index: usize,
-        remove_start_index: usize,
-        remove_stop_index: usize,
-    ) -> bool {
-        node_start_index < remove_stop_index && remove_start_index <= node_start_index
-    }
-
-    fn is_deletion_at_the_end_of_node(
-        node_start_index: usize,
-        node_stop_index: usize,
-        remove_start_index: usize,
-        _cremove_stop_index: usize,
-    ) -> bool {
-        node_stop_index > remove_start_index && remove_start_index >= node_start_index
+        let root = self.root.take();
+        let (left, rest) = self.split(root, start_index as usize);
+        let (_, right) = self.split(rest, length);
+        self.root = concat(left, right);
     }
 
     /*
@@ -234,93 +607,34 @@ impl PieceTable {
      */
 
     pub fn insert(&mut self, index: u32, text: String) {
-        let add_start_index = self.added.len();
-        self.added.push_str(&text);
-
-        let (node_where_it_got_inserted, index_node_where_it_got_inserted, text_index) =
-            self.get_node_where_it_got_inserted_and_index(index);
-
-        let new_nodes = self.build_new_nodes(
-            index,
-            text,
-            add_start_index,
-            node_where_it_got_inserted,
-            text_index,
-        );
-
-        self.nodes.splice(
-            index_node_where_it_got_inserted..index_node_where_it_got_inserted + 1,
-            new_nodes.into_iter().filter(|node| node.length != 0),
-        );
-    }
-
-    fn build_new_nodes(
-        &self,
-        index: u32,
-        text: String,
-        added_length: usize,
-        node_where_it_got_inserted: Node,
-        text_index: usize,
-    ) -> Vec<Node> {
-        let length_before_insertion_node = index - text_index as u32;
-        let node_before_insertion = Node {
-            node_type: node_where_it_got_inserted.node_type,
-            length: length_before_insertion_node as usize,
-            start: node_where_it_got_inserted.start,
-        };
-        let new_node = Node {
-            node_type: ADDED,
-            length: text.len(),
-            start: added_length as u32,
-        };
-        let length_after_insertion =
-            node_where_it_got_inserted.length - node_before_insertion.length;
-        let node_after_insertion = Node {
-            node_type: node_where_it_got_inserted.node_type,
-            length: length_after_insertion,
-            start: node_before_insertion.start + node_before_insertion.length as u32,
-        };
-        let is_node_at_the_end_added = node_before_insertion.start as usize
-            + node_before_insertion.length
-            == new_node.start as usize
-            && node_before_insertion.node_type == ADDED;
-
-        if is_node_at_the_end_added {
-            vec![
-                Node {
-                    node_type: node_before_insertion.node_type,
-                    start: node_before_insertion.start,
-                    length: node_before_insertion.length + new_node.length,
-                },
-                node_after_insertion,
-            ]
-        } else {
-            vec![node_before_insertion, new_node, node_after_insertion]
+        if text.is_empty() {
+            return;
         }
-    }
+        let add_start_index = self.push_added(&text);
 
-    fn get_node_where_it_got_inserted_and_index(&self, index: u32) -> (Node, usize, usize) {
-        let mut total_offset = 0;
-        let mut index_node_where_it_got_inserted = 0;
+        let root = self.root.take();
+        let (left, right) = self.split(root, index as usize);
 
-        let node_where_it_got_inserted = self
-            .nodes
-            .iter()
-            .find(|node| {
-                if (total_offset + node.length) as u32 >= index {
-                    return true;
-                }
-                total_offset += node.length;
-                index_node_where_it_got_inserted += 1;
-                false
-            })
-            .unwrap();
+        let is_node_at_the_end_added = match get_last_piece(&left) {
+            Some(last_piece) => {
+                last_piece.node_type == ADDED
+                    && last_piece.start as usize + last_piece.length == add_start_index
+            }
+            None => false,
+        };
 
-        (
-            *node_where_it_got_inserted,
-            index_node_where_it_got_inserted,
-            total_offset,
-        )
+        self.root = match left {
+            Some(left) if is_node_at_the_end_added => {
+                let (rest, last_piece) = split_last(*left);
+                let new_node =
+                    self.build_node(ADDED, last_piece.start, last_piece.length + text.len());
+                Some(join(rest, new_node, right))
+            }
+            left => {
+                let new_node = self.build_node(ADDED, add_start_index as u32, text.len());
+                Some(join(left, new_node, right))
+            }
+        };
     }
 }
 
@@ -377,14 +691,16 @@ mod tests {
                     node_type: ORIGINAL,
                     start: 0,
                     length: 14,
+                    line_breaks: 0,
                 },
                 Node {
                     node_type: ADDED,
                     start: 0,
                     length: 3,
+                    line_breaks: 0,
                 }
             ],
-            piece_table.nodes
+            piece_table.get_nodes()
         );
     }
 
@@ -481,6 +797,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn remove_within_an_added_node_should_keep_the_end_of_the_node() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("This is"));
+        piece_table = piece_table.push(String::from(" a"));
+        piece_table.insert(0, String::from(">> "));
+        piece_table.insert(12, String::from(" smxxall text"));
+
+        // When
+        piece_table.remove(15, 2);
+
+        // Then
+        let text = piece_table.get_text();
+        assert_eq!(text, String::from(">> This is a small text"));
+    }
+
+    #[test]
+    fn get_text_range_should_return_text_across_several_nodes() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("This is a text."));
+        piece_table = piece_table.push(String::from(" This is a second piece."));
+        piece_table.insert(10, String::from("new "));
+
+        // When
+        let result = piece_table.get_text_range(8, 12);
+
+        // Then
+        assert_eq!(result, String::from("a new text. "));
+    }
+
+    #[test]
+    fn get_text_range_should_return_nothing_for_an_empty_range() {
+        // Given
+        let piece_table = PieceTable::new(String::from("This is a text."));
+
+        // When
+        let result = piece_table.get_text_range(4, 0);
+
+        // Then
+        assert_eq!(result, String::new());
+    }
+
     #[test]
     fn insert_should_insert_text_in_the_content_first_node() {
         // Given
@@ -545,7 +903,7 @@ mod tests {
 
         // Then
         let text = piece_table.get_text();
-        assert_eq!(3, piece_table.nodes.len());
+        assert_eq!(3, piece_table.get_nodes().len());
         assert_eq!(text, String::from("This is a text for unit tests..."))
     }
 
@@ -597,11 +955,11 @@ mod tests {
             text,
             String::from("This is a text. This is a new second piece.")
         );
-        assert_eq!(4, piece_table.nodes.len());
+        assert_eq!(4, piece_table.get_nodes().len());
     }
 
     #[test]
-    fn should_find_node_where_it_got_inserted_and_its_index() {
+    fn split_should_cut_the_node_where_it_got_inserted() {
         // Given
         let input = String::from("This is a text");
         let push_str = String::from(".");
@@ -609,21 +967,28 @@ mod tests {
         let mut piece_table = PieceTable::new(input);
         piece_table = piece_table.push(push_str);
         piece_table = piece_table.push(push_str2);
-
-        let expected_node = piece_table.nodes.get(0).unwrap().clone();
+        let root = piece_table.root.clone();
 
         // When
-        let (result, result_index, text_index) =
-            piece_table.get_node_where_it_got_inserted_and_index(5);
+        let (left, right) = piece_table.split(root, 5);
 
         // Then
-        assert_eq!(0, result_index);
-        assert_eq!(expected_node, result);
-        assert_eq!(0, text_index);
+        let mut left_nodes = vec![];
+        let mut right_nodes = vec![];
+        collect_pieces(&left, &mut left_nodes);
+        collect_pieces(&right, &mut right_nodes);
+        assert_eq!(left_nodes, vec![piece_table.build_node(ORIGINAL, 0, 5)]);
+        assert_eq!(
+            right_nodes,
+            vec![
+                piece_table.build_node(ORIGINAL, 5, 9),
+                piece_table.build_node(ADDED, 0, 3)
+            ]
+        );
     }
 
     #[test]
-    fn should_find_node_where_it_got_inserted_and_its_index_even_on_added_nodes() {
+    fn split_should_cut_the_node_where_it_got_inserted_even_on_added_nodes() {
         // Given
         let input = String::from("This is a text");
         let push_str = String::from(". And this is another sentence");
@@ -631,16 +996,55 @@ mod tests {
         let mut piece_table = PieceTable::new(input);
         piece_table = piece_table.push(push_str);
         piece_table = piece_table.push(push_str2);
+        let root = piece_table.root.clone();
+
+        // When
+        let (left, right) = piece_table.split(root, 20);
+
+        // Then
+        let mut left_nodes = vec![];
+        let mut right_nodes = vec![];
+        collect_pieces(&left, &mut left_nodes);
+        collect_pieces(&right, &mut right_nodes);
+        assert_eq!(
+            left_nodes,
+            vec![
+                piece_table.build_node(ORIGINAL, 0, 14),
+                piece_table.build_node(ADDED, 0, 6)
+            ]
+        );
+        assert_eq!(right_nodes, vec![piece_table.build_node(ADDED, 6, 27)]);
+        assert_eq!(length(&left), 20);
+    }
 
-        let expected_node = piece_table.nodes.get(1).unwrap().clone();
+    #[test]
+    fn insert_should_keep_the_tree_balanced_after_many_edits() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("first line\nsecond line\n"));
+        let mut seed: u64 = 42;
+        let mut next_random = || {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) as usize
+        };
 
         // When
-        let (result, result_index, text_index) =
-            piece_table.get_node_where_it_got_inserted_and_index(20);
+        for i in 0..20_000 {
+            let index = next_random() % (piece_table.get_length() + 1);
+            if i % 3 == 2 && index < piece_table.get_length() {
+                piece_table.remove(index as u32, 1);
+            } else {
+                piece_table.insert(index as u32, String::from("x\n"));
+            }
+        }
+
         // Then
-        assert_eq!(1, result_index);
-        assert_eq!(expected_node, result);
-        assert_eq!(14, text_index);
+        let number_of_nodes = piece_table.get_nodes().len() as f64;
+        let max_avl_height = 1.45 * (number_of_nodes + 2.0).log2();
+        assert!((height(&piece_table.root) as f64) < max_avl_height);
+        assert_eq!(
+            piece_table.get_number_of_lines(),
+            piece_table.get_text().matches('\n').count() + 1
+        );
     }
 
     #[test]
@@ -653,12 +1057,12 @@ mod tests {
         // When
         let result = x == y;
         let result_1 = x == z;
-        let result_2 = x.nodes == y.nodes;
+        let result_2 = x.get_nodes() == y.get_nodes();
 
         // Then
-        assert_eq!(result, true);
-        assert_eq!(result_1, false);
-        assert_eq!(result_2, true);
+        assert!(result);
+        assert!(!result_1);
+        assert!(result_2);
     }
 
     #[test]
@@ -668,16 +1072,19 @@ mod tests {
             node_type: ORIGINAL,
             length: 2,
             start: 5,
+            line_breaks: 0,
         };
         let y = Node {
             node_type: ORIGINAL,
             length: 2,
             start: 5,
+            line_breaks: 0,
         };
         let z = Node {
             node_type: ORIGINAL,
             length: 2,
             start: 7,
+            line_breaks: 0,
         };
 
         // When
@@ -685,8 +1092,8 @@ mod tests {
         let result_1 = x == z;
 
         // Then
-        assert_eq!(result, true);
-        assert_eq!(result_1, false);
+        assert!(result);
+        assert!(!result_1);
     }
 
     #[test]
@@ -714,4 +1121,208 @@ mod tests {
         let number_of_line = piece_table.get_number_of_lines();
         assert_eq!(number_of_line, 3)
     }
+
+    #[test]
+    fn get_number_of_lines_should_count_line_breaks_of_inserted_text() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("first\nsecond"));
+
+        // When
+        piece_table.insert(5, String::from("\nnew\n"));
+
+        // Then
+        assert_eq!(piece_table.get_number_of_lines(), 4);
+    }
+
+    #[test]
+    fn get_number_of_lines_should_not_count_removed_line_breaks() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("first\nsecond\nthird"));
+
+        // When
+        piece_table.remove(5, 7);
+
+        // Then
+        assert_eq!(piece_table.get_number_of_lines(), 2);
+        assert_eq!(piece_table.get_text(), "first\nthird");
+    }
+
+    #[test]
+    fn get_line_start_index_should_find_line_breaks_in_several_nodes() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("first\nsecond\nthird"));
+        piece_table.insert(6, String::from("new\n"));
+
+        // When
+        let result = (0..5)
+            .map(|line_index| piece_table.get_line_start_index(line_index))
+            .collect::<Vec<u32>>();
+
+        // Then
+        assert_eq!(result, vec![0, 6, 10, 17, 22]);
+    }
+
+    #[test]
+    fn get_range_lines_should_only_return_requested_lines() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("first\nsecond\nthird"));
+        piece_table.insert(12, String::from("\nfourth"));
+
+        // When
+        let result = piece_table.get_range_lines(1, 3);
+
+        // Then
+        assert_eq!(result, vec!["second", "fourth"]);
+    }
+
+    #[test]
+    fn get_range_lines_should_stop_at_the_last_line() {
+        // Given
+        let piece_table = PieceTable::new(String::from("first\nsecond\n"));
+
+        // When
+        let result = piece_table.get_range_lines(1, 10);
+
+        // Then
+        assert_eq!(result, vec!["second", ""]);
+    }
+
+    #[test]
+    fn get_line_should_return_nothing_after_the_last_line() {
+        // Given
+        let piece_table = PieceTable::new(String::from("first\nsecond"));
+
+        // When
+        let result = piece_table.get_line(2);
+
+        // Then
+        assert_eq!(result, None);
+        assert_eq!(piece_table.get_line(1), Some(String::from("second")));
+    }
+
+    #[test]
+    fn insert_should_insert_text_when_everything_got_removed() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("x"));
+        piece_table.remove(0, 1);
+
+        // When
+        piece_table.insert(0, String::from("y\n"));
+
+        // Then
+        assert_eq!(piece_table.get_text(), "y\n");
+        assert_eq!(piece_table.get_number_of_lines(), 2);
+    }
+
+    #[test]
+    fn get_line_index_should_count_the_line_breaks_before_the_index() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("first\nsecond\nthird"));
+        piece_table.insert(8, String::from("\nadded\n"));
+
+        // When
+        let result = piece_table.get_line_index(16);
+
+        // Then
+        assert_eq!(piece_table.get_text(), "first\nse\nadded\ncond\nthird");
+        assert_eq!(result, 3);
+        assert_eq!(piece_table.get_line_index(0), 0);
+        assert_eq!(piece_table.get_line_index(5), 0);
+        assert_eq!(piece_table.get_line_index(6), 1);
+        assert_eq!(piece_table.get_line_index(26), 4);
+    }
+
+    #[test]
+    fn find_should_return_the_first_match_after_the_start_index() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("a text, a tex"));
+        piece_table.insert(13, String::from("t"));
+
+        // When
+        let result = piece_table.find("text", 3);
+
+        // Then
+        assert_eq!(result, Some(10));
+        assert_eq!(piece_table.find("text", 11), None);
+    }
+
+    #[test]
+    fn rfind_should_return_the_last_match_before_the_stop_index() {
+        // Given
+        let piece_table = PieceTable::new(String::from("été, été"));
+
+        // When
+        let result = piece_table.rfind("été", 9);
+
+        // Then
+        assert_eq!(result, Some(0));
+        assert_eq!(piece_table.rfind("été", 12), Some(7));
+    }
+
+    #[test]
+    fn find_should_return_the_matches_spanning_several_pieces() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("a tt, é"));
+        piece_table.insert(3, String::from("ex"));
+        piece_table.insert(10, String::from("é"));
+        let text = piece_table.get_text();
+        assert_eq!(text, "a text, éé");
+
+        for query in &["text", "xt, é", "éé", "t, éé", "a", "z"] {
+            for index in 0..=text.len() {
+                // When
+                let found = piece_table.find(query, index as u32);
+                let rfound = piece_table.rfind(query, index as u32);
+
+                // Then
+                if text.is_char_boundary(index) {
+                    let expected = text[index..].find(query).map(|i| (index + i) as u32);
+                    assert_eq!(found, expected);
+                    let expected = text[..index].rfind(query).map(|i| i as u32);
+                    assert_eq!(rfound, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn piece_walk_should_visit_the_pieces_in_order_from_an_index() {
+        // Given
+        let mut piece_table = PieceTable::new(String::from("0123456789"));
+        for i in 0..40 {
+            piece_table.insert((i * 7) % (piece_table.get_length() as u32), i.to_string());
+        }
+        let nodes = piece_table.get_nodes();
+        let starts = nodes
+            .iter()
+            .scan(0, |start, node| {
+                *start += node.length;
+                Some(*start - node.length)
+            })
+            .collect::<Vec<usize>>();
+
+        for index in 0..=piece_table.get_length() {
+            // When
+            let forward = PieceWalk::forward(&piece_table.root, index).collect::<Vec<_>>();
+            let backward = PieceWalk::backward(&piece_table.root, index).collect::<Vec<_>>();
+
+            // Then
+            let first = starts.iter().rposition(|&start| start <= index);
+            let expected_forward = match first {
+                Some(first) if index < piece_table.get_length() => first,
+                _ => nodes.len(),
+            };
+            assert_eq!(forward.len(), nodes.len() - expected_forward);
+            for (i, (start, node)) in forward.iter().enumerate() {
+                assert_eq!(*start, starts[expected_forward + i]);
+                assert_eq!(*node, nodes[expected_forward + i]);
+            }
+            let expected_backward = starts.iter().filter(|&&start| start < index).count();
+            assert_eq!(backward.len(), expected_backward);
+            for (i, (start, node)) in backward.iter().enumerate() {
+                assert_eq!(*start, starts[expected_backward - 1 - i]);
+                assert_eq!(*node, nodes[expected_backward - 1 - i]);
+            }
+        }
+    }
 }
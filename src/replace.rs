@@ -0,0 +1,159 @@
+use regex::Regex;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replacement {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Replace {
+    regex: Regex,
+    template: String,
+    pub index: usize,
+    pub number_of_replacements: usize,
+}
+
+impl PartialEq for Replace {
+    fn eq(&self, other: &Replace) -> bool {
+        self.regex.as_str() == other.regex.as_str()
+            && self.template == other.template
+            && self.index == other.index
+            && self.number_of_replacements == other.number_of_replacements
+    }
+}
+
+impl Replace {
+    pub fn new(pattern: &str, template: &str) -> Result<Replace, regex::Error> {
+        Ok(Replace {
+            regex: Regex::new(pattern)?,
+            template: String::from(template),
+            index: 0,
+            number_of_replacements: 0,
+        })
+    }
+
+    fn build_replacement(&self, captures: &regex::Captures) -> Replacement {
+        let found = captures.get(0).expect("a match has a whole capture group");
+        let mut text = String::new();
+        captures.expand(&self.template, &mut text);
+        Replacement {
+            start: found.start(),
+            end: found.end(),
+            text,
+        }
+    }
+
+    pub fn find_next(&self, text: &str) -> Option<Replacement> {
+        if self.index > text.len() {
+            return None;
+        }
+        self.regex
+            .captures_at(text, self.index)
+            .map(|captures| self.build_replacement(&captures))
+    }
+
+    pub fn find_all(&self, text: &str) -> Vec<Replacement> {
+        let mut replacements = vec![];
+        let mut index = self.index;
+        while index <= text.len() {
+            let replacement = match self.regex.captures_at(text, index) {
+                Some(captures) => self.build_replacement(&captures),
+                None => break,
+            };
+            index = get_next_index(text, replacement.end, replacement.start == replacement.end);
+            replacements.push(replacement);
+        }
+        replacements
+    }
+
+    pub fn skip(&mut self, text: &str, replacement: &Replacement) {
+        let is_empty_match = replacement.start == replacement.end;
+        self.index = get_next_index(text, replacement.end, is_empty_match);
+    }
+
+    pub fn mark_replaced(&mut self, text: &str, replacement: &Replacement) {
+        let is_empty_match = replacement.start == replacement.end;
+        let end = replacement.start + replacement.text.len();
+        self.index = get_next_index(text, end, is_empty_match);
+        self.number_of_replacements += 1;
+    }
+}
+
+fn get_next_index(text: &str, index: usize, is_empty_match: bool) -> usize {
+    if !is_empty_match {
+        return index;
+    }
+    match text[index..].chars().next() {
+        Some(c) => index + c.len_utf8(),
+        None => index + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_next_should_expand_the_capture_groups() {
+        // Given
+        let replace = Replace::new(r"(\w+)@(\w+)", "$2 at ${1}").unwrap();
+
+        // When
+        let result = replace.find_next("mail: bob@home");
+
+        // Then
+        assert_eq!(
+            result,
+            Some(Replacement {
+                start: 6,
+                end: 14,
+                text: String::from("home at bob"),
+            })
+        );
+    }
+
+    #[test]
+    fn find_all_should_return_every_match_after_the_index() {
+        // Given
+        let mut replace = Replace::new("a+", "b").unwrap();
+        replace.index = 2;
+
+        // When
+        let result = replace.find_all("aa a aaa");
+
+        // Then
+        let starts: Vec<usize> = result.iter().map(|r| r.start).collect();
+        assert_eq!(starts, vec![3, 5]);
+    }
+
+    #[test]
+    fn find_all_should_not_loop_on_empty_matches() {
+        // Given
+        let replace = Replace::new("x*", "-").unwrap();
+
+        // When
+        let result = replace.find_all("ab");
+
+        // Then
+        let starts: Vec<usize> = result.iter().map(|r| r.start).collect();
+        assert_eq!(starts, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn mark_replaced_should_continue_after_the_replacement_text() {
+        // Given
+        let mut replace = Replace::new("cat", "dog").unwrap();
+        let text = "cat cat";
+        let replacement = replace.find_next(text).unwrap();
+
+        // When
+        replace.mark_replaced("dog cat", &replacement);
+
+        // Then
+        assert_eq!(replace.index, 3);
+        assert_eq!(replace.number_of_replacements, 1);
+        assert_eq!(replace.find_next("dog cat").unwrap().start, 4);
+    }
+}
@@ -0,0 +1,46 @@
+use std::io::{self, Write};
+use std::mem;
+use std::panic;
+use std::sync::Mutex;
+
+use libc::{tcgetattr, tcsetattr, termios, STDOUT_FILENO, TCSANOW};
+use termion::{cursor, screen};
+
+pub const ENABLE_BRACKETED_PASTE: &str = "\x1b[?2004h";
+const DISABLE_BRACKETED_PASTE: &str = "\x1b[?2004l";
+
+static ORIGINAL_MODE: Mutex<Option<termios>> = Mutex::new(None);
+
+pub fn save_terminal_mode() {
+    let mut termios: termios = unsafe { mem::zeroed() };
+    if unsafe { tcgetattr(STDOUT_FILENO, &mut termios) } == 0 {
+        if let Ok(mut original_mode) = ORIGINAL_MODE.lock() {
+            *original_mode = Some(termios);
+        }
+    }
+}
+
+pub fn restore_terminal() {
+    let mut stdout = io::stdout();
+    let _ = write!(
+        stdout,
+        "{}{}{}",
+        DISABLE_BRACKETED_PASTE,
+        screen::ToMainScreen,
+        cursor::Show
+    );
+    let _ = stdout.flush();
+    if let Ok(original_mode) = ORIGINAL_MODE.lock() {
+        if let Some(termios) = original_mode.as_ref() {
+            unsafe { tcsetattr(STDOUT_FILENO, TCSANOW, termios) };
+        }
+    }
+}
+
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+}
@@ -1,11 +1,16 @@
 extern crate termion;
 
-use std::io::Write;
+use std::io::{self, Write};
+use std::ops::Range;
 
 use termion::{color, style};
 
-use cursor::*;
-use editor::Editor;
+use column::*;
+use editor::{format_number_of_lines, Editor, Mode};
+use minibuffer::Minibuffer;
+
+const NUMBER_OF_BOTTOM_LINES: u16 = 2;
+const QUIT_PROMPT: &str = "Save changes before quitting? (y)es, (n)o, (c)ancel";
 
 pub fn print_line<W: Write>(
     stream: &mut W,
@@ -13,8 +18,8 @@ pub fn print_line<W: Write>(
     terminal_line_nb: u16,
     file_line_nb: u16,
     content: &str,
-    cursor: &CursorPosition,
-) {
+    cursor_position: termion::cursor::Goto,
+) -> io::Result<()> {
     let line_nb_displayed = render_line_nb(left_pad, file_line_nb);
     write!(
         stream,
@@ -24,12 +29,11 @@ pub fn print_line<W: Write>(
         line_nb_displayed,
         style::Reset,
         content,
-        termion::cursor::Goto(left_pad + cursor.x + 2, cursor.y + 1),
+        cursor_position,
     )
-    .unwrap();
 }
 
-pub fn print_first_line<W: Write>(stream: &mut W) {
+pub fn print_first_line<W: Write>(stream: &mut W) -> io::Result<()> {
     write!(
         stream,
         "{}{}{}{}Rustor{}: ESC to quit{}",
@@ -40,7 +44,6 @@ pub fn print_first_line<W: Write>(stream: &mut W) {
         style::Reset,
         termion::cursor::Goto(1, 2)
     )
-    .unwrap();
 }
 
 pub fn get_number_of_chars_of_u16(num: u16) -> u16 {
@@ -48,41 +51,358 @@ pub fn get_number_of_chars_of_u16(num: u16) -> u16 {
     base.len() as u16
 }
 
-pub fn print_text<W: Write>(stream: &mut W, editor: &Editor) {
-    let (terminal_width, terminal_height) = termion::terminal_size().unwrap();
+fn print_wrapped_row<W: Write>(
+    stream: &mut W,
+    left_pad: u16,
+    terminal_line_nb: u16,
+    content: &str,
+    cursor_position: termion::cursor::Goto,
+) -> io::Result<()> {
+    let blanks = (0..left_pad + 2).map(|_| ' ').collect::<String>();
+    write!(
+        stream,
+        "{}{}{}{}",
+        termion::cursor::Goto(1, terminal_line_nb + 1),
+        blanks,
+        content,
+        cursor_position,
+    )
+}
+
+pub fn get_editor_height(terminal_height: u16) -> u16 {
+    terminal_height - NUMBER_OF_BOTTOM_LINES
+}
+
+fn print_message_line<W: Write>(
+    stream: &mut W,
+    message: Option<&str>,
+    terminal_width: u16,
+    terminal_height: u16,
+) -> io::Result<()> {
+    let message = message.unwrap_or_default();
+    let width = terminal_width as usize;
+    write!(
+        stream,
+        "{}{}",
+        termion::cursor::Goto(1, terminal_height),
+        render_line(message, 1, 0, width),
+    )
+}
+
+fn render_status_bar(editor: &Editor, width: usize) -> String {
+    let file_name = editor.file_name.as_deref().unwrap_or("[No Name]");
+    let modified = if editor.is_modified() { " [+]" } else { "" };
+    let readonly = if editor.is_readonly { " [RO]" } else { "" };
+    let left = format!(" {}{}{}", file_name, modified, readonly);
+    let right = format!(
+        "Ln {}, Col {}  {}  {}  {} ",
+        editor.cursor.get_y_position_in_file(),
+        editor.cursor.x,
+        format_number_of_lines(editor.get_number_of_lines()),
+        editor.file_format.line_ending.get_name(),
+        editor.file_format.encoding.get_name(),
+    );
+    let right_width = right.len();
+    let left_width = width.saturating_sub(right_width);
+    let mut status_bar = render_line(&left, 1, 0, left_width);
+    status_bar.push_str(&render_line(&right, 1, 0, width - left_width));
+    status_bar
+}
+
+fn print_status_bar<W: Write>(
+    stream: &mut W,
+    editor: &Editor,
+    terminal_width: u16,
+    terminal_height: u16,
+) -> io::Result<()> {
+    write!(
+        stream,
+        "{}{}{}{}",
+        termion::cursor::Goto(1, terminal_height - 1),
+        style::Invert,
+        render_status_bar(editor, terminal_width as usize),
+        style::Reset,
+    )
+}
+
+pub fn print_text<W: Write>(stream: &mut W, editor: &mut Editor) -> io::Result<()> {
+    let (terminal_width, terminal_height) = termion::terminal_size()?;
     let number_of_lines = editor.get_number_of_lines();
     let left_pad = get_number_of_chars_of_u16(number_of_lines as u16);
+    let text_width = (terminal_width - left_pad - 2) as usize;
+    let text_height = get_editor_height(terminal_height) as usize - 1;
+    editor.text_width = text_width;
+    if let Some(sequence) = editor.take_clipboard_sequence() {
+        write!(stream, "{}", sequence)?;
+    }
+    print_status_bar(stream, editor, terminal_width, terminal_height)?;
+    let mut minibuffer_column = None;
+    match &editor.mode {
+        Mode::Edit => {
+            let message = editor.message.as_deref();
+            print_message_line(stream, message, terminal_width, terminal_height)?;
+        }
+        Mode::QuitPrompt => {
+            print_message_line(stream, Some(QUIT_PROMPT), terminal_width, terminal_height)?;
+        }
+        Mode::ReplaceConfirm { replacement, .. } => {
+            let prompt = format!(
+                "Replace with \"{}\"? (y)es, (n)o, (a)ll, (.) this one, (q)uit",
+                replacement.text
+            );
+            print_message_line(stream, Some(&prompt), terminal_width, terminal_height)?;
+        }
+        Mode::SaveAs { minibuffer, .. }
+        | Mode::GoToLine { minibuffer }
+        | Mode::Search { minibuffer, .. }
+        | Mode::ReplacePattern { minibuffer }
+        | Mode::ReplaceWith { minibuffer, .. } => {
+            let (content, column) = render_minibuffer(minibuffer, terminal_width as usize);
+            print_message_line(stream, Some(&content), terminal_width, terminal_height)?;
+            minibuffer_column = Some(column);
+        }
+    }
+    if editor.config.soft_wrap {
+        print_wrapped_text(stream, editor, left_pad, terminal_width, text_height)?;
+    } else {
+        print_unwrapped_text(stream, editor, left_pad, terminal_width, text_height)?;
+    }
+    if let Some(column) = minibuffer_column {
+        write!(
+            stream,
+            "{}",
+            termion::cursor::Goto(column as u16 + 1, terminal_height)
+        )?;
+    }
+    Ok(())
+}
+
+fn render_minibuffer(minibuffer: &Minibuffer, width: usize) -> (String, usize) {
+    let content = format!("{}{}", minibuffer.label, minibuffer.input);
+    let label_width = get_screen_column(&minibuffer.label, u16::MAX, 1);
+    let screen_column = label_width + get_screen_column(&minibuffer.input, minibuffer.x, 1);
+    let first_column = (screen_column + 1).saturating_sub(width);
+    (
+        render_line(&content, 1, first_column, width),
+        screen_column - first_column,
+    )
+}
+
+fn print_unwrapped_text<W: Write>(
+    stream: &mut W,
+    editor: &mut Editor,
+    left_pad: u16,
+    terminal_width: u16,
+    text_height: usize,
+) -> io::Result<()> {
+    let text_width = editor.text_width;
+    let tab_stop = editor.config.tab_stop;
     let white_line = (0..terminal_width).map(|_| ' ').collect::<String>();
-    for (index, l) in editor
-        .get_editor_lines(terminal_height as usize - 1)
-        .iter()
-        .enumerate()
-    {
-        let mut line_content = l.clone();
-        line_content.push_str(&white_line);
-        line_content.truncate((terminal_width - left_pad - 2) as usize);
+    let lines = editor.get_editor_lines(text_height);
+    scroll_horizontally(editor, &lines, text_width);
+    let x_offset = editor.cursor.x_offset as usize;
+    let cursor_position = get_cursor_position_on_screen(editor, &lines, left_pad);
+    for (index, l) in lines.iter().enumerate() {
+        let file_line_index = index + editor.cursor.y_offset as usize;
+        let highlights = get_highlights(editor, file_line_index, l);
+        let line_content = render_highlighted_line(l, tab_stop, x_offset, text_width, &highlights);
         print_line(
             stream,
             left_pad,
             index as u16 + 1,
             index as u16 + 1 + editor.cursor.y_offset,
             &line_content,
-            &editor.cursor,
-        )
+            cursor_position,
+        )?;
+    }
+
+    for terminal_line_nb in lines.len() + 1..=text_height {
+        write!(
+            stream,
+            "{}{}{}",
+            termion::cursor::Goto(1, terminal_line_nb as u16 + 1),
+            white_line,
+            cursor_position,
+        )?;
+    }
+    Ok(())
+}
+
+fn print_wrapped_text<W: Write>(
+    stream: &mut W,
+    editor: &mut Editor,
+    left_pad: u16,
+    terminal_width: u16,
+    text_height: usize,
+) -> io::Result<()> {
+    let text_width = editor.text_width;
+    let tab_stop = editor.config.tab_stop;
+    let white_line = (0..terminal_width).map(|_| ' ').collect::<String>();
+    editor.cursor.x_offset = 0;
+    scroll_vertically_in_wrapped_lines(editor, text_height);
+    let lines = editor.get_editor_lines(text_height);
+    let cursor_position = get_wrapped_cursor_position_on_screen(editor, &lines, left_pad);
+    let mut terminal_line_nb = 1;
+    for (index, l) in lines.iter().enumerate() {
+        let file_line_index = index + editor.cursor.y_offset as usize;
+        let highlights = get_highlights(editor, file_line_index, l);
+        let row_start_columns = get_row_start_columns(l, tab_stop, text_width);
+        for (row, &row_start_column) in row_start_columns.iter().enumerate() {
+            if terminal_line_nb as usize > text_height {
+                return Ok(());
+            }
+            let row_width = match row_start_columns.get(row + 1) {
+                Some(next_row_start_column) => next_row_start_column - row_start_column,
+                None => text_width,
+            };
+            let mut row_content =
+                render_highlighted_line(l, tab_stop, row_start_column, row_width, &highlights);
+            row_content.extend((row_width..text_width).map(|_| ' '));
+            if row == 0 {
+                print_line(
+                    stream,
+                    left_pad,
+                    terminal_line_nb,
+                    index as u16 + 1 + editor.cursor.y_offset,
+                    &row_content,
+                    cursor_position,
+                )?;
+            } else {
+                print_wrapped_row(
+                    stream,
+                    left_pad,
+                    terminal_line_nb,
+                    &row_content,
+                    cursor_position,
+                )?;
+            }
+            terminal_line_nb += 1;
+        }
     }
 
-    if number_of_lines < terminal_height as usize - 1 {
+    while terminal_line_nb as usize <= text_height {
         write!(
             stream,
             "{}{}{}",
-            termion::cursor::Goto(1, number_of_lines as u16 + 2),
+            termion::cursor::Goto(1, terminal_line_nb + 1),
             white_line,
-            termion::cursor::Goto(left_pad + editor.cursor.x + 2, editor.cursor.y + 1),
-        )
-        .unwrap();
+            cursor_position,
+        )?;
+        terminal_line_nb += 1;
+    }
+    Ok(())
+}
+
+fn get_search_highlights(line: &str, query: Option<&str>) -> Vec<Range<usize>> {
+    match query {
+        Some(query) => line
+            .match_indices(query)
+            .map(|(index, _)| index..index + query.len())
+            .collect(),
+        None => vec![],
+    }
+}
+
+fn get_highlights(editor: &Editor, file_line_index: usize, line: &str) -> Vec<Highlight> {
+    let mut highlights = vec![];
+    if let Some(range) = editor.get_selection_in_line(file_line_index, line.len()) {
+        highlights.push(Highlight {
+            range,
+            kind: HighlightKind::Selection,
+        });
+    }
+    let mut match_ranges = get_search_highlights(line, editor.get_search_query());
+    if let Some((line_index, range)) = editor.get_replace_match() {
+        if line_index == file_line_index {
+            match_ranges.push(range);
+        }
+    }
+    highlights.extend(match_ranges.into_iter().map(|range| Highlight {
+        range,
+        kind: HighlightKind::Match,
+    }));
+    highlights
+}
+
+fn get_number_of_rows_before_cursor(editor: &Editor, lines: &[String]) -> Vec<usize> {
+    let cursor = &editor.cursor;
+    let tab_stop = editor.config.tab_stop;
+    let mut number_of_rows: Vec<usize> = lines
+        .iter()
+        .take(cursor.y as usize)
+        .map(|l| get_row_start_columns(l, tab_stop, editor.text_width).len())
+        .collect();
+    if let Some(line) = lines.get(cursor.y as usize - 1) {
+        let row_start_columns = get_row_start_columns(line, tab_stop, editor.text_width);
+        let screen_column = get_screen_column(line, cursor.x, tab_stop);
+        number_of_rows[cursor.y as usize - 1] =
+            get_row_of_screen_column(&row_start_columns, screen_column) + 1;
+    }
+    number_of_rows
+}
+
+fn scroll_vertically_in_wrapped_lines(editor: &mut Editor, text_height: usize) {
+    let lines = editor.get_editor_lines(editor.cursor.y as usize);
+    let number_of_rows = get_number_of_rows_before_cursor(editor, &lines);
+    let mut visible_rows: usize = number_of_rows.iter().sum();
+    let mut first_line = 0;
+    while visible_rows > text_height && first_line + 1 < number_of_rows.len() {
+        visible_rows -= number_of_rows[first_line];
+        first_line += 1;
+    }
+    editor.cursor.y_offset += first_line as u16;
+    editor.cursor.y -= first_line as u16;
+}
+
+fn get_wrapped_cursor_position_on_screen(
+    editor: &Editor,
+    lines: &[String],
+    left_pad: u16,
+) -> termion::cursor::Goto {
+    let cursor = &editor.cursor;
+    let rows_before_cursor: usize = get_number_of_rows_before_cursor(editor, lines)
+        .iter()
+        .sum::<usize>()
+        .max(1);
+    let screen_column = match lines.get(cursor.y as usize - 1) {
+        Some(line) => {
+            let tab_stop = editor.config.tab_stop;
+            let row_start_columns = get_row_start_columns(line, tab_stop, editor.text_width);
+            let screen_column = get_screen_column(line, cursor.x, tab_stop);
+            let row = get_row_of_screen_column(&row_start_columns, screen_column);
+            screen_column - row_start_columns[row]
+        }
+        None => 0,
+    };
+    termion::cursor::Goto(
+        left_pad + screen_column as u16 + 3,
+        rows_before_cursor as u16 + 1,
+    )
+}
+
+fn get_cursor_screen_column(editor: &Editor, lines: &[String]) -> usize {
+    let cursor = &editor.cursor;
+    match lines.get(cursor.y as usize - 1) {
+        Some(line) => get_screen_column(line, cursor.x, editor.config.tab_stop),
+        None => 0,
     }
 }
 
+fn scroll_horizontally(editor: &mut Editor, lines: &[String], text_width: usize) {
+    let screen_column = get_cursor_screen_column(editor, lines);
+    editor.cursor.scroll_horizontally(screen_column, text_width);
+}
+
+fn get_cursor_position_on_screen(
+    editor: &Editor,
+    lines: &[String],
+    left_pad: u16,
+) -> termion::cursor::Goto {
+    let cursor = &editor.cursor;
+    let screen_column = get_cursor_screen_column(editor, lines) as u16 - cursor.x_offset;
+    termion::cursor::Goto(left_pad + screen_column + 3, cursor.y + 1)
+}
+
 fn render_line_nb(left_pad: u16, line_nb: u16) -> String {
     let nb_of_blanks_before_line_nb = left_pad - get_number_of_chars_of_u16(line_nb);
     let mut line_nb_displayed = String::new();
@@ -101,7 +421,7 @@ mod tests {
     #[test]
     fn test_get_number_of_chars_of_u16_one_digit() {
         // Given
-        let nb = 4 as u16;
+        let nb = 4_u16;
 
         // When
         let result = get_number_of_chars_of_u16(nb);
@@ -113,7 +433,7 @@ mod tests {
     #[test]
     fn test_get_number_of_chars_of_u16_two_digits() {
         // Given
-        let nb = 99 as u16;
+        let nb = 99_u16;
 
         // When
         let result = get_number_of_chars_of_u16(nb);
@@ -125,7 +445,7 @@ mod tests {
     #[test]
     fn test_get_number_of_chars_of_u16_three_digits() {
         // Given
-        let nb = 666 as u16;
+        let nb = 666_u16;
 
         // When
         let result = get_number_of_chars_of_u16(nb);
@@ -172,4 +492,127 @@ mod tests {
         // Then
         assert_eq!(result, "   5");
     }
+
+    #[test]
+    fn test_get_cursor_position_on_screen_after_tabs_and_wide_characters() {
+        // Given
+        let lines = vec![String::from("first"), String::from("\t日本x")];
+        let mut editor = Editor::from(lines.clone());
+        editor.cursor.x = 4;
+        editor.cursor.y = 2;
+
+        // When
+        let termion::cursor::Goto(x, y) = get_cursor_position_on_screen(&editor, &lines, 1);
+
+        // Then
+        assert_eq!(x, 12);
+        assert_eq!(y, 3);
+    }
+
+    #[test]
+    fn test_scroll_horizontally_should_keep_the_cursor_on_screen() {
+        // Given
+        let lines = vec![String::from("a very long line that does not fit")];
+        let mut editor = Editor::from(lines.clone());
+        editor.cursor.x = 21;
+
+        // When
+        scroll_horizontally(&mut editor, &lines, 10);
+        let termion::cursor::Goto(x, _) = get_cursor_position_on_screen(&editor, &lines, 1);
+
+        // Then
+        assert_eq!(editor.cursor.x_offset, 11);
+        assert_eq!(x, 13);
+    }
+
+    #[test]
+    fn test_get_wrapped_cursor_position_on_screen_should_count_the_rows_of_previous_lines() {
+        // Given
+        let lines = vec![String::from("abcdefghij"), String::from("klmnopq")];
+        let mut editor = Editor::from(lines.clone());
+        editor.config.soft_wrap = true;
+        editor.text_width = 4;
+        editor.cursor.x = 6;
+        editor.cursor.y = 2;
+
+        // When
+        let termion::cursor::Goto(x, y) = get_wrapped_cursor_position_on_screen(&editor, &lines, 1);
+
+        // Then
+        assert_eq!(x, 5);
+        assert_eq!(y, 6);
+    }
+
+    #[test]
+    fn test_scroll_vertically_in_wrapped_lines_should_keep_the_cursor_row_on_screen() {
+        // Given
+        let lines = vec![
+            String::from("abcdefghij"),
+            String::from("klmnopq"),
+            String::from("r"),
+        ];
+        let mut editor = Editor::from(lines);
+        editor.config.soft_wrap = true;
+        editor.text_width = 4;
+        editor.cursor.y = 3;
+
+        // When
+        scroll_vertically_in_wrapped_lines(&mut editor, 3);
+
+        // Then
+        assert_eq!(editor.cursor.y_offset, 1);
+        assert_eq!(editor.cursor.y, 2);
+    }
+
+    #[test]
+    fn test_render_status_bar_for_an_unnamed_buffer() {
+        // Given
+        let editor = Editor::from(vec![String::from("first"), String::from("second")]);
+
+        // When
+        let result = render_status_bar(&editor, 50);
+
+        // Then
+        assert_eq!(result, " [No Name]        Ln 1, Col 1  2 lines  LF  UTF-8 ");
+    }
+
+    #[test]
+    fn test_render_status_bar_should_show_the_modified_file() {
+        // Given
+        let mut editor =
+            Editor::from(vec![String::new()]).with_file_name(Some(String::from("notes.txt")));
+        editor.insert('a', 50);
+
+        // When
+        let result = render_status_bar(&editor, 50);
+
+        // Then
+        assert_eq!(result, " notes.txt [+]     Ln 1, Col 2  1 line  LF  UTF-8 ");
+    }
+
+    #[test]
+    fn test_render_minibuffer_should_scroll_to_the_cursor() {
+        // Given
+        let minibuffer = Minibuffer::new("Save as: ").with_input("some/long/path.txt");
+
+        // When
+        let (content, column) = render_minibuffer(&minibuffer, 10);
+
+        // Then
+        assert_eq!(content, "/path.txt ");
+        assert_eq!(column, 9);
+    }
+
+    #[test]
+    fn test_get_search_highlights_should_cover_every_match_of_the_line() {
+        // Given
+        let line = "été, été";
+
+        // When
+        let result = get_search_highlights(line, Some("été"));
+
+        // Then
+        assert_eq!(result, vec![0..5, 7..12]);
+        assert!(get_search_highlights(line, None).is_empty());
+    }
 }
@@ -0,0 +1,99 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+fn get_char_class(grapheme: &str, word_chars: &str) -> CharClass {
+    match grapheme.chars().next() {
+        Some(c) if c.is_whitespace() => CharClass::Whitespace,
+        Some(c) if c.is_alphanumeric() || word_chars.contains(c) => CharClass::Word,
+        _ => CharClass::Punctuation,
+    }
+}
+
+pub fn find_next_word_end(line: &str, index: usize, word_chars: &str) -> Option<usize> {
+    let mut graphemes = line[index..]
+        .grapheme_indices(true)
+        .map(|(i, grapheme)| (index + i, get_char_class(grapheme, word_chars)))
+        .skip_while(|(_, class)| *class == CharClass::Whitespace)
+        .peekable();
+    let word_class = graphemes.peek()?.1;
+    let end = graphemes
+        .find(|(_, class)| *class != word_class)
+        .map_or(line.len(), |(i, _)| i);
+    Some(end)
+}
+
+pub fn find_previous_word_start(line: &str, index: usize, word_chars: &str) -> Option<usize> {
+    let mut graphemes = line[..index]
+        .grapheme_indices(true)
+        .rev()
+        .map(|(i, grapheme)| (i, get_char_class(grapheme, word_chars)))
+        .skip_while(|(_, class)| *class == CharClass::Whitespace)
+        .peekable();
+    let (mut start, word_class) = *graphemes.peek()?;
+    for (i, class) in graphemes {
+        if class != word_class {
+            break;
+        }
+        start = i;
+    }
+    Some(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_next_word_end_should_skip_whitespace_then_a_word() {
+        // Given
+        let line = "let  my_value = 1;";
+
+        // When
+        let results = [
+            find_next_word_end(line, 0, "_"),
+            find_next_word_end(line, 3, "_"),
+            find_next_word_end(line, 14, "_"),
+            find_next_word_end(line, 18, "_"),
+        ];
+
+        // Then
+        assert_eq!(results, [Some(3), Some(13), Some(15), None]);
+    }
+
+    #[test]
+    fn find_next_word_end_should_use_the_configured_word_chars() {
+        // Given
+        let line = "my-value";
+
+        // When
+        let default_result = find_next_word_end(line, 0, "_");
+        let result = find_next_word_end(line, 0, "-");
+
+        // Then
+        assert_eq!(default_result, Some(2));
+        assert_eq!(result, Some(8));
+    }
+
+    #[test]
+    fn find_previous_word_start_should_skip_whitespace_then_a_word() {
+        // Given
+        let line = "  héllo.world  ";
+
+        // When
+        let results = [
+            find_previous_word_start(line, 16, "_"),
+            find_previous_word_start(line, 9, "_"),
+            find_previous_word_start(line, 8, "_"),
+            find_previous_word_start(line, 2, "_"),
+        ];
+
+        // Then
+        assert_eq!(results, [Some(9), Some(8), Some(2), None]);
+    }
+}